@@ -0,0 +1,475 @@
+//! Default options from the environment and a config file
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use clap::parser::ValueSource;
+use clap::{Arg, ArgAction, Command};
+use midimon::error::MidimonError;
+
+/// Environment variable with default options
+const ENV_VAR: &str = "MIDIMON_OPTS";
+
+/// Value of an option in the config file
+enum ConfigValue {
+    Flag(bool),
+    Values(Vec<String>),
+}
+
+/// Return the command line arguments with the defaults from the config file and the environment
+/// inserted before the given arguments
+/// Options given later override earlier ones, so the precedence is command line, environment
+/// and config file. Defaults conflicting with an option of higher precedence are dropped.
+pub fn args_with_defaults(command: &Command) -> Result<Vec<OsString>, MidimonError> {
+    let mut defaults = Vec::new();
+
+    if let Some(path) = config_path() {
+        match std::fs::read_to_string(&path) {
+            Ok(text) => defaults.extend(parse_config(&text, command).map_err(|err| {
+                MidimonError::InvalidArgument(format!("{}: {}", path.display(), err))
+            })?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => (),
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    if let Some(options) = std::env::var_os(ENV_VAR) {
+        let options = split_options(&options.to_string_lossy())
+            .map_err(|err| MidimonError::InvalidArgument(format!("{}: {}", ENV_VAR, err)))?;
+        defaults = drop_conflicting(defaults, &options, command);
+        defaults.extend(options);
+    }
+
+    let given: Vec<OsString> = std::env::args_os().skip(1).collect();
+    let mut args: Vec<OsString> = std::env::args_os().take(1).collect();
+    args.extend(drop_conflicting(defaults, &given, command));
+    args.extend(given);
+
+    Ok(args)
+}
+
+/// Return the default arguments without the options conflicting with the given arguments
+/// The given arguments are parsed on their own to find the options set by them. If they can't be
+/// parsed, all defaults are kept and the error is reported when parsing all arguments.
+fn drop_conflicting(
+    defaults: Vec<OsString>,
+    given: &[OsString],
+    command: &Command,
+) -> Vec<OsString> {
+    let matches = match command.clone().try_get_matches_from(
+        std::iter::once(OsString::from("midimon")).chain(given.iter().cloned()),
+    ) {
+        Ok(matches) => matches,
+        Err(_) => return defaults,
+    };
+
+    let given_args: Vec<&Arg> = command
+        .get_arguments()
+        .filter(|arg| matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine))
+        .collect();
+
+    let conflicts = |arg: &Arg| {
+        given_args.iter().any(|given_arg| {
+            command
+                .get_arg_conflicts_with(arg)
+                .iter()
+                .any(|other| other.get_id() == given_arg.get_id())
+                || command
+                    .get_arg_conflicts_with(given_arg)
+                    .iter()
+                    .any(|other| other.get_id() == arg.get_id())
+        })
+    };
+
+    let mut args = Vec::new();
+    let mut keep = true;
+
+    // An option and the values following it are kept or dropped together
+    for default in defaults {
+        let options = option_args(&default.to_string_lossy(), command);
+        if !options.is_empty() || default.to_string_lossy().starts_with("--") {
+            keep = !options.into_iter().any(conflicts);
+        }
+        if keep {
+            args.push(default);
+        }
+    }
+
+    args
+}
+
+/// Return the options set by an argument, or nothing if it is a value
+/// An argument can set several options when short flags are combined, like `-qn`.
+fn option_args<'a>(argument: &str, command: &'a Command) -> Vec<&'a Arg> {
+    if let Some(long) = argument.strip_prefix("--") {
+        let name = long.split_once('=').map_or(long, |(name, _)| name);
+        return command
+            .get_arguments()
+            .filter(|arg| arg.get_long() == Some(name))
+            .collect();
+    }
+
+    let mut args = Vec::new();
+    if let Some(shorts) = argument.strip_prefix('-') {
+        for short in shorts.chars() {
+            match command
+                .get_arguments()
+                .find(|arg| arg.get_short() == Some(short))
+            {
+                Some(arg) => {
+                    args.push(arg);
+                    // The rest of the argument is the value of the option
+                    if arg.get_action().takes_values() {
+                        break;
+                    }
+                }
+                None => break,
+            }
+        }
+    }
+
+    args
+}
+
+/// Return the path of the config file
+/// This is `midimon/config.toml` in `XDG_CONFIG_HOME`, `~/.config` or `APPDATA` on Windows
+fn config_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))?;
+
+    Some(config_dir.join("midimon").join("config.toml"))
+}
+
+/// Parse the config file and return the options as arguments
+/// Each line sets an option by its long name to a string, number, boolean or array of these,
+/// e.g. `format = "min-hex"` or `ignore = ["clock", "sensing"]`. Flags are set with `true`.
+fn parse_config(text: &str, command: &Command) -> Result<Vec<OsString>, String> {
+    let mut args = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let error = |message: &str| format!("line {}: {}", index + 1, message);
+
+        if line.starts_with('[') {
+            return Err(error("tables are not supported"));
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| error("expected option = value"))?;
+        let key = key.trim();
+
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(key) && !matches!(key, "help" | "version"))
+            .ok_or_else(|| error(&format!("unknown option \"{}\"", key)))?;
+
+        match parse_value(value.trim()).map_err(|err| error(&err))? {
+            ConfigValue::Flag(true) => args.push(OsString::from(format!("--{}", key))),
+            ConfigValue::Flag(false) => (),
+            ConfigValue::Values(values) if matches!(arg.get_action(), ArgAction::Append) => {
+                for value in values {
+                    args.push(OsString::from(format!("--{}={}", key, value)));
+                }
+            }
+            ConfigValue::Values(values) => {
+                args.push(OsString::from(format!("--{}", key)));
+                args.extend(values.into_iter().map(OsString::from));
+            }
+        }
+    }
+
+    Ok(args)
+}
+
+/// Parse the value of an option in the config file
+fn parse_value(value: &str) -> Result<ConfigValue, String> {
+    match value {
+        "true" => return Ok(ConfigValue::Flag(true)),
+        "false" => return Ok(ConfigValue::Flag(false)),
+        _ => (),
+    }
+
+    let items = match value.strip_prefix('[') {
+        Some(array) => {
+            let array = array
+                .strip_suffix(']')
+                .ok_or_else(|| String::from("unterminated array"))?;
+            split_outside_quotes(array, |c| c == ',', true)?
+                .into_iter()
+                .filter(|item| !item.trim().is_empty())
+                .collect()
+        }
+        None => vec![String::from(value)],
+    };
+
+    let values = items
+        .iter()
+        .map(|item| {
+            let item = item.trim();
+            for quote in ['"', '\''] {
+                if let Some(text) = item.strip_prefix(quote) {
+                    return text
+                        .strip_suffix(quote)
+                        .map(String::from)
+                        .ok_or_else(|| String::from("unterminated string"));
+                }
+            }
+            if item.is_empty() {
+                Err(String::from("missing value"))
+            } else {
+                Ok(String::from(item))
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(ConfigValue::Values(values))
+}
+
+/// Return a line without a comment starting with `#` outside of quotes
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+
+    for (index, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '#') => return &line[..index],
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            _ => (),
+        }
+    }
+
+    line
+}
+
+/// Split options from the environment at whitespace outside of quotes and remove the quotes
+fn split_options(options: &str) -> Result<Vec<OsString>, String> {
+    Ok(split_outside_quotes(options, char::is_whitespace, false)?
+        .into_iter()
+        .filter(|option| !option.is_empty())
+        .map(OsString::from)
+        .collect())
+}
+
+/// Split text at separators outside of single or double quotes
+fn split_outside_quotes(
+    text: &str,
+    separator: fn(char) -> bool,
+    keep_quotes: bool,
+) -> Result<Vec<String>, String> {
+    let mut parts = vec![String::new()];
+    let mut quote = None;
+
+    for c in text.chars() {
+        match (quote, c) {
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                if !keep_quotes {
+                    continue;
+                }
+            }
+            (Some(open), _) if c == open => {
+                quote = None;
+                if !keep_quotes {
+                    continue;
+                }
+            }
+            (None, _) if separator(c) => {
+                parts.push(String::new());
+                continue;
+            }
+            _ => (),
+        }
+        parts.last_mut().expect("No part to append to").push(c);
+    }
+
+    if quote.is_some() {
+        return Err(String::from("unterminated quote"));
+    }
+
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Command with a subset of the monitor options
+    fn command() -> Command {
+        Command::new("midimon")
+            .args_override_self(true)
+            .arg(Arg::new("port").short('p').long("port"))
+            .arg(Arg::new("virtual").long("virtual").conflicts_with("port"))
+            .arg(Arg::new("format").short('f').long("format"))
+            .arg(
+                Arg::new("template")
+                    .long("template")
+                    .conflicts_with("format"),
+            )
+            .arg(Arg::new("ignore").short('i').long("ignore").num_args(1..))
+            .arg(
+                Arg::new("only")
+                    .long("only")
+                    .num_args(1..)
+                    .conflicts_with("ignore"),
+            )
+            .arg(
+                Arg::new("match-hex")
+                    .long("match-hex")
+                    .action(ArgAction::Append),
+            )
+            .arg(
+                Arg::new("quiet")
+                    .short('q')
+                    .long("quiet")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("transpose")
+                    .long("transpose")
+                    .allow_negative_numbers(true),
+            )
+    }
+
+    fn os_strings(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn strip_comments() {
+        assert_eq!(
+            strip_comment("format = \"min\" # comment"),
+            "format = \"min\" "
+        );
+        assert_eq!(strip_comment("# comment"), "");
+        assert_eq!(
+            strip_comment("template = \"#{no}\""),
+            "template = \"#{no}\""
+        );
+        assert_eq!(strip_comment("template = '\"#' # x"), "template = '\"#' ");
+    }
+
+    #[test]
+    fn split_quoted() {
+        assert_eq!(
+            split_outside_quotes("a \"b c\" 'd e'", char::is_whitespace, false).unwrap(),
+            vec!["a", "b c", "d e"]
+        );
+        assert_eq!(
+            split_outside_quotes("\"a,b\", c", |c| c == ',', true).unwrap(),
+            vec!["\"a,b\"", " c"]
+        );
+        assert!(split_outside_quotes("\"a b", char::is_whitespace, false).is_err());
+    }
+
+    #[test]
+    fn config_values() {
+        assert!(matches!(parse_value("true"), Ok(ConfigValue::Flag(true))));
+        assert!(matches!(parse_value("false"), Ok(ConfigValue::Flag(false))));
+        assert!(
+            matches!(parse_value("\"min-hex\""), Ok(ConfigValue::Values(values)) if values == ["min-hex"])
+        );
+        assert!(matches!(parse_value("-12"), Ok(ConfigValue::Values(values)) if values == ["-12"]));
+        assert!(matches!(
+            parse_value("[\"clock\", 'sensing', ]"),
+            Ok(ConfigValue::Values(values)) if values == ["clock", "sensing"]
+        ));
+        assert!(parse_value("[\"clock\"").is_err());
+        assert!(parse_value("\"clock").is_err());
+        assert!(parse_value("[\"clock\", , \"sensing\"]").is_ok());
+    }
+
+    #[test]
+    fn config_file() {
+        let text = "# defaults\n\
+                    format = \"min-hex\"\n\
+                    ignore = [\"clock\", \"sensing\"]  # realtime\n\
+                    match-hex = [\"B0 07 ??\", \"C0 ??\"]\n\
+                    \n\
+                    quiet = true\n\
+                    transpose = false\n";
+        assert_eq!(
+            parse_config(text, &command()).unwrap(),
+            os_strings(&[
+                "--format",
+                "min-hex",
+                "--ignore",
+                "clock",
+                "sensing",
+                "--match-hex=B0 07 ??",
+                "--match-hex=C0 ??",
+                "--quiet",
+            ])
+        );
+
+        assert_eq!(
+            parse_config("colour = true", &command()).unwrap_err(),
+            "line 1: unknown option \"colour\""
+        );
+        assert_eq!(
+            parse_config("\n[monitor]", &command()).unwrap_err(),
+            "line 2: tables are not supported"
+        );
+        assert_eq!(
+            parse_config("quiet", &command()).unwrap_err(),
+            "line 1: expected option = value"
+        );
+    }
+
+    #[test]
+    fn conflicting_defaults() {
+        let defaults = os_strings(&["--ignore", "clock", "sensing", "--format", "min-hex", "-q"]);
+
+        assert_eq!(
+            drop_conflicting(defaults.clone(), &os_strings(&["--only", "cc"]), &command()),
+            os_strings(&["--format", "min-hex", "-q"])
+        );
+        assert_eq!(
+            drop_conflicting(
+                defaults.clone(),
+                &os_strings(&["--template={data}"]),
+                &command()
+            ),
+            os_strings(&["--ignore", "clock", "sensing", "-q"])
+        );
+        assert_eq!(
+            drop_conflicting(defaults.clone(), &os_strings(&["-f", "min"]), &command()),
+            defaults
+        );
+
+        // Values starting with a hyphen belong to the option before them
+        assert_eq!(
+            drop_conflicting(
+                os_strings(&["-p", "1", "--transpose", "-12"]),
+                &os_strings(&["--virtual", "in"]),
+                &command()
+            ),
+            os_strings(&["--transpose", "-12"])
+        );
+        assert_eq!(
+            drop_conflicting(
+                os_strings(&["-qp2", "--format", "min"]),
+                &os_strings(&["--virtual", "in"]),
+                &command()
+            ),
+            os_strings(&["--format", "min"])
+        );
+
+        // Defaults are kept when the arguments are invalid, clap reports the error later
+        assert_eq!(
+            drop_conflicting(
+                defaults.clone(),
+                &os_strings(&["--only", "cc", "--bogus"]),
+                &command()
+            ),
+            defaults
+        );
+    }
+}
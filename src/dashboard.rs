@@ -0,0 +1,388 @@
+//! Live dashboard drawn in place of the scrolling message list
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use midimon::display::value_bar;
+use midimon::messages::{MidiMessage, NoteNaming, Status};
+
+/// Interval for redrawing the dashboard
+const REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Time after which the activity meter of a channel has faded out
+const ACTIVITY_DECAY: Duration = Duration::from_secs(1);
+
+/// Number of seconds shown in the message rate graph
+const RATE_HISTORY: usize = 60;
+
+/// Range of notes shown in the piano roll, covering an 88-key piano
+const PIANO_ROLL: std::ops::RangeInclusive<u8> = 21..=108;
+
+/// Width of the activity meters in characters
+const METER_WIDTH: usize = 16;
+
+/// Maximum number of controllers shown per channel
+const MAX_CONTROLLERS: usize = 6;
+
+/// Terminal size in columns and rows used if it can't be determined
+const DEFAULT_SIZE: (usize, usize) = (80, 24);
+
+/// State of the dashboard collected from the received messages
+struct Dashboard {
+    note_naming: NoteNaming,
+    last_activity: [Option<Instant>; 16],
+    message_counts: [u64; 16],
+    held_notes: BTreeSet<(u8, u8)>,
+    controllers: BTreeMap<(u8, u8), u8>,
+    rates: VecDeque<u32>,
+    current_rate: u32,
+    second_start: Instant,
+}
+
+impl Dashboard {
+    fn new(note_naming: NoteNaming) -> Self {
+        Self {
+            note_naming,
+            last_activity: [None; 16],
+            message_counts: [0; 16],
+            held_notes: BTreeSet::new(),
+            controllers: BTreeMap::new(),
+            rates: VecDeque::new(),
+            current_rate: 0,
+            second_start: Instant::now(),
+        }
+    }
+
+    /// Update the state with a received message
+    fn process(&mut self, message: &[u8], now: Instant) {
+        let msg = MidiMessage::from_array(message);
+        self.current_rate += 1;
+
+        let Some(channel) = msg.channel() else {
+            return;
+        };
+
+        self.last_activity[channel as usize] = Some(now);
+        self.message_counts[channel as usize] += 1;
+
+        match (msg.status(), msg.get_data(1), msg.get_data(2)) {
+            (Status::NoteOn, Some(note), Some(velocity)) if velocity > 0 => {
+                self.held_notes.insert((channel, note));
+            }
+            (Status::NoteOn | Status::NoteOff, Some(note), Some(_)) => {
+                self.held_notes.remove(&(channel, note));
+            }
+            (Status::ControlChange, Some(123), Some(_)) => {
+                self.held_notes
+                    .retain(|(held_channel, _)| *held_channel != channel);
+            }
+            (Status::ControlChange, Some(controller), Some(value)) => {
+                self.controllers.insert((channel, controller), value);
+            }
+            _ => (),
+        }
+    }
+
+    /// Advance the message rate history to the current time
+    fn tick(&mut self, now: Instant) {
+        while now.duration_since(self.second_start) >= Duration::from_secs(1) {
+            self.rates.push_back(self.current_rate);
+            if self.rates.len() > RATE_HISTORY {
+                self.rates.pop_front();
+            }
+            self.current_rate = 0;
+            self.second_start += Duration::from_secs(1);
+        }
+    }
+
+    /// Return the complete screen contents for a terminal of the given size
+    /// Lines are cut at the terminal width and at the last row, so that nothing wraps or scrolls
+    /// and the next frame is drawn over this one.
+    fn render(&self, now: Instant, columns: usize, rows: usize) -> String {
+        let lines: Vec<String> = self
+            .lines(now)
+            .iter()
+            .take(rows)
+            .map(|line| line.chars().take(columns).collect())
+            .collect();
+
+        // Clear the rest of each line and the screen below to remove leftovers of the last frame
+        format!("\x1b[H{}\x1b[K\x1b[J", lines.join("\x1b[K\n"))
+    }
+
+    /// Return the lines of the dashboard without limiting them to the terminal size
+    fn lines(&self, now: Instant) -> Vec<String> {
+        let mut lines = vec![
+            format!(
+                "midimon dashboard    Rate: {} messages/s    Press Ctrl-C to exit",
+                self.rates.back().copied().unwrap_or(0)
+            ),
+            String::new(),
+            String::from("Channel activity"),
+        ];
+
+        for channel in 0..16u8 {
+            let level = self.last_activity[channel as usize].map_or(0, |last| {
+                let elapsed = now.duration_since(last).as_secs_f64();
+                (127.0 * (1.0 - elapsed / ACTIVITY_DECAY.as_secs_f64())).max(0.0) as u8
+            });
+            let notes: Vec<String> = self
+                .held_notes
+                .iter()
+                .filter(|(held_channel, _)| *held_channel == channel)
+                .filter_map(|(_, note)| {
+                    MidiMessage::from_array(&[Status::NoteOn as u8 | channel, *note, 127])
+                        .note_name_with(&self.note_naming)
+                })
+                .collect();
+            lines.push(format!(
+                "  Ch:{:>2}  {}  {:>8}  {}",
+                channel + 1,
+                value_bar(level, METER_WIDTH),
+                self.message_counts[channel as usize],
+                notes.join(" ")
+            ));
+        }
+
+        lines.push(String::new());
+        lines.push(String::from("Controllers"));
+        for channel in 0..16u8 {
+            let values: Vec<String> = self
+                .controllers
+                .iter()
+                .filter(|((cc_channel, _), _)| *cc_channel == channel)
+                .take(MAX_CONTROLLERS)
+                .map(|((_, controller), value)| format!("{:>3}:{:<3}", controller, value))
+                .collect();
+            if !values.is_empty() {
+                lines.push(format!("  Ch:{:>2}  {}", channel + 1, values.join("  ")));
+            }
+        }
+
+        lines.push(String::new());
+        lines.push(String::from("Held notes"));
+        for channel in 0..16u8 {
+            if !self.held_notes.iter().any(|(held, _)| *held == channel) {
+                continue;
+            }
+            let roll: String = PIANO_ROLL
+                .map(|note| {
+                    if self.held_notes.contains(&(channel, note)) {
+                        '█'
+                    } else if matches!(note % 12, 1 | 3 | 6 | 8 | 10) {
+                        '·'
+                    } else {
+                        ' '
+                    }
+                })
+                .collect();
+            lines.push(format!("  Ch:{:>2}  |{}|", channel + 1, roll));
+        }
+
+        lines.push(String::new());
+        lines.push(String::from("Message rate (last 60 s)"));
+        lines.push(format!("  {}", rate_graph(&self.rates)));
+
+        lines
+    }
+}
+
+/// Alternate screen with a hidden cursor, restored when dropped or when a thread panics
+struct AlternateScreen;
+
+impl AlternateScreen {
+    /// Switch to the alternate screen and hide the cursor
+    fn enter() -> Self {
+        // Restore the terminal before the panic message is shown, it would be lost otherwise
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            restore_screen();
+            default_hook(info);
+        }));
+
+        print!("\x1b[?1049h\x1b[?25l");
+        std::io::stdout().flush().ok();
+
+        Self
+    }
+}
+
+impl Drop for AlternateScreen {
+    fn drop(&mut self) {
+        restore_screen();
+    }
+}
+
+/// Show the cursor and switch back to the original screen
+fn restore_screen() {
+    print!("\x1b[?25h\x1b[?1049l");
+    std::io::stdout().flush().ok();
+}
+
+/// Return the size of the terminal connected to stdout in columns and rows
+#[cfg(unix)]
+fn terminal_size() -> (usize, usize) {
+    // SAFETY: A zeroed `winsize` is a valid value, the ioctl only writes to it
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+
+    if result == -1 || size.ws_col == 0 || size.ws_row == 0 {
+        DEFAULT_SIZE
+    } else {
+        (size.ws_col as usize, size.ws_row as usize)
+    }
+}
+
+/// Return the size of the terminal in columns and rows
+/// The size is taken from the `COLUMNS` and `LINES` environment variables on this platform.
+#[cfg(not(unix))]
+fn terminal_size() -> (usize, usize) {
+    let size = |name: &str| {
+        std::env::var(name)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|size| *size > 0)
+    };
+
+    (
+        size("COLUMNS").unwrap_or(DEFAULT_SIZE.0),
+        size("LINES").unwrap_or(DEFAULT_SIZE.1),
+    )
+}
+
+/// Return per-second message counts as a graph of block characters, scaled to the maximum
+fn rate_graph(rates: &VecDeque<u32>) -> String {
+    const LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let max = rates.iter().copied().max().unwrap_or(0).max(1);
+
+    rates
+        .iter()
+        .map(|&rate| {
+            if rate == 0 {
+                ' '
+            } else {
+                LEVELS[((rate as usize * LEVELS.len() - 1) / max as usize).min(LEVELS.len() - 1)]
+            }
+        })
+        .collect()
+}
+
+/// Draw the dashboard on the alternate screen until monitoring is stopped
+/// Messages are received from the input callbacks via the channel
+pub fn run(messages: Receiver<Vec<u8>>, running: Arc<AtomicBool>, note_naming: NoteNaming) {
+    let mut dashboard = Dashboard::new(note_naming);
+    let mut stdout = std::io::stdout();
+    let mut last_refresh = Instant::now();
+    let _screen = AlternateScreen::enter();
+
+    while running.load(Ordering::SeqCst) {
+        match messages.recv_timeout(REFRESH_INTERVAL) {
+            Ok(message) => dashboard.process(&message, Instant::now()),
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let now = Instant::now();
+        dashboard.tick(now);
+
+        if now.duration_since(last_refresh) >= REFRESH_INTERVAL {
+            let (columns, rows) = terminal_size();
+            write!(stdout, "{}", dashboard.render(now, columns, rows))
+                .and_then(|_| stdout.flush())
+                .ok();
+            last_refresh = now;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn held_notes_and_controllers() {
+        let now = Instant::now();
+        let mut dashboard = Dashboard::new(NoteNaming::default());
+
+        for message in [
+            &[0x90, 60, 100][..],
+            &[0x90, 64, 100],
+            &[0x91, 67, 100],
+            &[0x80, 60, 0],
+            &[0x90, 62, 0],
+            &[0xB0, 7, 100],
+            &[0xB0, 7, 90],
+            &[0xB1, 123, 0],
+            &[0xF8],
+        ] {
+            dashboard.process(message, now);
+        }
+
+        assert_eq!(
+            dashboard.held_notes.iter().copied().collect::<Vec<_>>(),
+            [(0, 64)]
+        );
+        assert_eq!(
+            dashboard.controllers.iter().collect::<Vec<_>>(),
+            [(&(0, 7), &90)]
+        );
+        assert_eq!(dashboard.message_counts[..2], [6, 2]);
+        assert_eq!(dashboard.current_rate, 9);
+        assert_eq!(dashboard.last_activity[0], Some(now));
+        assert_eq!(dashboard.last_activity[2], None);
+    }
+
+    #[test]
+    fn rate_history() {
+        let start = Instant::now();
+        let mut dashboard = Dashboard::new(NoteNaming::default());
+        dashboard.second_start = start;
+
+        dashboard.process(&[0xF8], start);
+        dashboard.process(&[0xF8], start);
+        dashboard.tick(start + Duration::from_millis(2500));
+        assert_eq!(dashboard.rates, [2, 0]);
+        assert_eq!(dashboard.current_rate, 0);
+
+        dashboard.tick(start + Duration::from_secs(RATE_HISTORY as u64 + 10));
+        assert_eq!(dashboard.rates.len(), RATE_HISTORY);
+    }
+
+    #[test]
+    fn rate_graphs() {
+        assert_eq!(rate_graph(&VecDeque::new()), "");
+        assert_eq!(rate_graph(&VecDeque::from([0, 1, 4, 8])), " ▁▄█");
+    }
+
+    #[test]
+    fn screen_size() {
+        let now = Instant::now();
+        let mut dashboard = Dashboard::new(NoteNaming::default());
+        dashboard.process(&[0x90, 60, 100], now);
+        dashboard.process(&[0xB0, 7, 100], now);
+
+        let lines = dashboard.lines(now);
+        assert!(
+            lines[3].starts_with("  Ch: 1  ████████████████         2  C3"),
+            "{}",
+            lines[3]
+        );
+        assert!(lines.iter().any(|line| line == "  Ch: 1    7:100"));
+        assert!(lines.iter().any(|line| line.chars().count() > 80));
+
+        let screen = dashboard.render(now, 80, 24);
+        let rows: Vec<&str> = screen
+            .trim_start_matches("\x1b[H")
+            .trim_end_matches("\x1b[K\x1b[J")
+            .split("\x1b[K\n")
+            .collect();
+        assert_eq!(rows.len(), 24);
+        assert!(rows.iter().all(|row| row.chars().count() <= 80));
+        assert_eq!(rows[3], lines[3]);
+    }
+}
@@ -0,0 +1,531 @@
+//! Stateful decoders for multi-message sequences
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use crate::messages::{MidiMessage, MtcField, Status};
+
+/// Kind of parameter selected via controller sequences
+#[derive(Copy, Clone, PartialEq)]
+pub enum ParameterKind {
+    Registered,
+    NonRegistered,
+}
+
+/// Result of feeding a control change into the parameter decoder
+pub enum ParameterEvent {
+    /// Parameter number was selected, nothing to display yet
+    Select,
+    /// Data entry for the selected parameter with its 14-bit number and value
+    Value(ParameterKind, u16, u16),
+}
+
+/// Per-channel decoder state for RPN and NRPN sequences
+#[derive(Copy, Clone, Default)]
+pub struct ParameterState {
+    kind: Option<ParameterKind>,
+    number: u16,
+    value: u16,
+}
+
+impl ParameterState {
+    /// Process a control change and return an event if it belongs to a parameter sequence
+    pub fn process(&mut self, controller: u8, value: u8) -> Option<ParameterEvent> {
+        let value = value as u16;
+
+        match controller {
+            99 | 101 => {
+                let kind = if controller == 99 {
+                    ParameterKind::NonRegistered
+                } else {
+                    ParameterKind::Registered
+                };
+                if self.kind != Some(kind) {
+                    self.number = 0;
+                }
+                self.kind = Some(kind);
+                self.number = (self.number & 0x7F) | (value << 7);
+                self.value = 0;
+                Some(ParameterEvent::Select)
+            }
+            98 | 100 => {
+                let kind = if controller == 98 {
+                    ParameterKind::NonRegistered
+                } else {
+                    ParameterKind::Registered
+                };
+                if self.kind != Some(kind) {
+                    self.number = 0;
+                }
+                self.kind = Some(kind);
+                self.number = (self.number & 0x3F80) | value;
+                self.value = 0;
+                if kind == ParameterKind::Registered && self.number == 0x3FFF {
+                    // RPN null, deselects the current parameter
+                    self.kind = None;
+                }
+                Some(ParameterEvent::Select)
+            }
+            6 => {
+                let kind = self.kind?;
+                self.value = value << 7;
+                Some(ParameterEvent::Value(kind, self.number, self.value))
+            }
+            38 => {
+                let kind = self.kind?;
+                self.value = (self.value & 0x3F80) | value;
+                Some(ParameterEvent::Value(kind, self.number, self.value))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Time in microseconds a coarse controller value is paired with a fine value
+pub const CC14_TIMEOUT: u64 = 1_000_000;
+
+/// Per-channel pairing state for 14-bit control changes
+#[derive(Copy, Clone, Default)]
+pub struct Cc14State {
+    msb: [Option<(u8, u64)>; 32],
+}
+
+impl Cc14State {
+    /// Process a control change and return controller number and 14-bit value
+    /// when a fine value completes a recently received coarse value
+    pub fn process(&mut self, controller: u8, value: u8, timestamp: u64) -> Option<(u8, u16)> {
+        match controller {
+            0..=31 => {
+                self.msb[controller as usize] = Some((value, timestamp));
+                None
+            }
+            32..=63 => {
+                let controller = controller - 32;
+                let (msb, msb_timestamp) = self.msb[controller as usize]?;
+                if timestamp.saturating_sub(msb_timestamp) > CC14_TIMEOUT {
+                    self.msb[controller as usize] = None;
+                    return None;
+                }
+                Some((controller, ((msb as u16) << 7) | value as u16))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Number of timing clock messages per quarter note
+pub const CLOCKS_PER_QUARTER: usize = 24;
+
+/// Clock interval in microseconds after which tempo averaging is restarted
+pub const CLOCK_TIMEOUT: u64 = 1_000_000;
+
+/// Tempo estimation state from timing clock messages
+#[derive(Default)]
+pub struct ClockState {
+    timestamps: VecDeque<u64>,
+}
+
+impl ClockState {
+    /// Register a clock message and return the estimated tempo in BPM
+    pub fn tick(&mut self, timestamp: u64) -> Option<f64> {
+        if let Some(&last) = self.timestamps.back() {
+            if timestamp.saturating_sub(last) > CLOCK_TIMEOUT || timestamp < last {
+                self.timestamps.clear();
+            }
+        }
+
+        self.timestamps.push_back(timestamp);
+        if self.timestamps.len() > CLOCKS_PER_QUARTER + 1 {
+            self.timestamps.pop_front();
+        }
+
+        let first = *self.timestamps.front()?;
+        let intervals = self.timestamps.len() as u64 - 1;
+        if intervals == 0 || timestamp == first {
+            return None;
+        }
+
+        let interval = (timestamp - first) as f64 / intervals as f64;
+        Some(60e6 / (interval * CLOCKS_PER_QUARTER as f64))
+    }
+}
+
+/// Timecode assembly state from MTC quarter frame messages
+#[derive(Default)]
+pub struct MtcState {
+    values: [u8; 8],
+    received: u8,
+}
+
+impl MtcState {
+    /// Register a quarter frame piece and return the timecode when all 8 pieces are complete
+    /// The timecode is returned as hours, minutes, seconds, frames and frame rate
+    pub fn piece(&mut self, field: MtcField, value: u8) -> Option<(u8, u8, u8, u8, &'static str)> {
+        let index = field as usize;
+
+        if field == MtcField::FramesLow {
+            self.received = 0;
+        }
+
+        self.values[index] = value & 0x0F;
+        self.received |= 1 << index;
+
+        if field != MtcField::HoursHighAndRate || self.received != 0xFF {
+            return None;
+        }
+
+        self.received = 0;
+
+        let v = &self.values;
+        let frames = v[0] | (v[1] & 0x01) << 4;
+        let seconds = v[2] | (v[3] & 0x03) << 4;
+        let minutes = v[4] | (v[5] & 0x03) << 4;
+        let hours = v[6] | (v[7] & 0x01) << 4;
+        let rate = match (v[7] >> 1) & 0x03 {
+            0 => "24 fps",
+            1 => "25 fps",
+            2 => "29.97 fps",
+            _ => "30 fps",
+        };
+
+        Some((hours, minutes, seconds, frames, rate))
+    }
+}
+
+/// Notes currently held per channel with the timestamps of their note ons
+#[derive(Default)]
+pub struct HeldNotes {
+    notes: BTreeMap<(u8, u8), u64>,
+}
+
+impl HeldNotes {
+    /// Process a message and return if the held notes changed
+    /// All Sound Off and All Notes Off release all notes of their channel
+    pub fn process(&mut self, msg: &MidiMessage, timestamp: u64) -> bool {
+        let (Some(channel), Some(data1), Some(data2)) =
+            (msg.channel(), msg.get_data(1), msg.get_data(2))
+        else {
+            return false;
+        };
+
+        match msg.status() {
+            Status::NoteOn if data2 > 0 => {
+                if self.notes.contains_key(&(channel, data1)) {
+                    return false;
+                }
+                self.notes.insert((channel, data1), timestamp);
+                true
+            }
+            Status::NoteOn | Status::NoteOff => self.notes.remove(&(channel, data1)).is_some(),
+            Status::ControlChange if data1 == 120 || data1 == 123 => {
+                let count = self.notes.len();
+                self.notes
+                    .retain(|(held_channel, _), _| *held_channel != channel);
+                self.notes.len() != count
+            }
+            _ => false,
+        }
+    }
+
+    /// Return channel, note and note on timestamp of all held notes
+    pub fn iter(&self) -> impl Iterator<Item = (u8, u8, u64)> + '_ {
+        self.notes
+            .iter()
+            .map(|((channel, note), timestamp)| (*channel, *note, *timestamp))
+    }
+
+    /// Return the held note numbers of all channels
+    pub fn notes(&self) -> BTreeSet<u8> {
+        self.notes.keys().map(|(_, note)| *note).collect()
+    }
+}
+
+/// Watch for stuck notes held too long and for too many notes held at once
+pub struct NoteWatch {
+    held: HeldNotes,
+    max_duration: Option<u64>,
+    poly_limit: Option<usize>,
+    reported: BTreeSet<(u8, u8, u64)>,
+    over_limit: bool,
+}
+
+impl NoteWatch {
+    /// Create a watch with the maximum duration in microseconds and the maximum number of notes
+    pub fn new(max_duration: Option<u64>, poly_limit: Option<usize>) -> Self {
+        Self {
+            held: HeldNotes::default(),
+            max_duration,
+            poly_limit,
+            reported: BTreeSet::new(),
+            over_limit: false,
+        }
+    }
+
+    /// Process a message and return the number of held notes when it exceeds the limit
+    /// The limit is reported again only after the number dropped to the limit
+    pub fn process(&mut self, msg: &MidiMessage, timestamp: u64) -> Option<usize> {
+        if !self.held.process(msg, timestamp) {
+            return None;
+        }
+
+        let count = self.held.iter().count();
+        let over_limit = self.poly_limit.is_some_and(|limit| count > limit);
+        let exceeded = over_limit && !self.over_limit;
+        self.over_limit = over_limit;
+
+        exceeded.then_some(count)
+    }
+
+    /// Return channel, note and duration of notes held longer than the maximum duration
+    /// Each stuck note is only returned once
+    pub fn stuck_notes(&mut self, timestamp: u64) -> Vec<(u8, u8, u64)> {
+        let Some(max_duration) = self.max_duration else {
+            return Vec::new();
+        };
+
+        let held: BTreeSet<(u8, u8, u64)> = self.held.iter().collect();
+        self.reported.retain(|note| held.contains(note));
+
+        let mut stuck = Vec::new();
+        for (channel, note, start) in held {
+            let duration = timestamp.saturating_sub(start);
+            if duration > max_duration && self.reported.insert((channel, note, start)) {
+                stuck.push((channel, note, duration));
+            }
+        }
+
+        stuck
+    }
+}
+
+/// Order of MTC quarter frame pieces to detect dropped messages
+#[derive(Default)]
+pub struct MtcSequence {
+    last: Option<MtcField>,
+}
+
+impl MtcSequence {
+    /// Register a quarter frame piece and return the number of pieces skipped since the last one
+    /// Pieces are expected in ascending order, descending order is accepted for reverse playback
+    pub fn piece(&mut self, field: MtcField) -> Option<u8> {
+        let last = self.last.replace(field)? as u8;
+        let skipped = (field as u8).wrapping_sub(last).wrapping_sub(1) & 0x07;
+
+        if skipped == 0 || (last.wrapping_sub(field as u8) & 0x07) == 1 {
+            None
+        } else {
+            Some(skipped)
+        }
+    }
+}
+
+/// Per-channel bank select state for program changes
+#[derive(Copy, Clone, Default)]
+pub struct BankState {
+    msb: Option<u8>,
+    lsb: Option<u8>,
+}
+
+impl BankState {
+    /// Process a control change and remember bank select values
+    pub fn process(&mut self, controller: u8, value: u8) {
+        match controller {
+            0 => self.msb = Some(value),
+            32 => self.lsb = Some(value),
+            _ => (),
+        }
+    }
+
+    /// Return bank MSB and LSB if any bank select was received
+    /// A missing part of the bank number is returned as 0
+    pub fn bank(&self) -> Option<(u8, u8)> {
+        match (self.msb, self.lsb) {
+            (None, None) => None,
+            (msb, lsb) => Some((msb.unwrap_or(0), lsb.unwrap_or(0))),
+        }
+    }
+}
+
+/// Result of pairing a note off with its note on
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NoteDuration {
+    /// Time in microseconds the note was held
+    Held(u64),
+    /// No matching note on was received before
+    Orphaned,
+}
+
+/// Start times of active notes to determine note durations
+/// Retriggered notes are paired with their note offs in order of arrival
+#[derive(Default)]
+pub struct NoteDurations {
+    active: BTreeMap<(u8, u8), VecDeque<u64>>,
+}
+
+impl NoteDurations {
+    /// Process a message and return the duration for note offs,
+    /// including note ons with velocity 0
+    pub fn process(&mut self, msg: &MidiMessage, timestamp: u64) -> Option<NoteDuration> {
+        let channel = msg.channel()?;
+        let note = msg.get_data(1)?;
+        let velocity = msg.get_data(2)?;
+
+        match msg.status() {
+            Status::NoteOn if velocity > 0 => {
+                self.active
+                    .entry((channel, note))
+                    .or_default()
+                    .push_back(timestamp);
+                None
+            }
+            Status::NoteOn | Status::NoteOff => {
+                let starts = self.active.entry((channel, note)).or_default();
+                let start = starts.pop_front();
+                if starts.is_empty() {
+                    self.active.remove(&(channel, note));
+                }
+                Some(match start {
+                    Some(start) => NoteDuration::Held(timestamp.saturating_sub(start)),
+                    None => NoteDuration::Orphaned,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(
+        durations: &mut NoteDurations,
+        message: &[u8],
+        timestamp: u64,
+    ) -> Option<NoteDuration> {
+        durations.process(&MidiMessage::from_array(message), timestamp)
+    }
+
+    #[test]
+    fn note_durations() {
+        let mut durations = NoteDurations::default();
+        assert_eq!(process(&mut durations, &[0x90, 60, 100], 1000), None);
+        assert_eq!(process(&mut durations, &[0x91, 60, 100], 2000), None);
+        assert_eq!(
+            process(&mut durations, &[0x80, 60, 0], 5000),
+            Some(NoteDuration::Held(4000))
+        );
+        assert_eq!(
+            process(&mut durations, &[0x91, 60, 0], 9000),
+            Some(NoteDuration::Held(7000))
+        );
+    }
+
+    #[test]
+    fn note_durations_retrigger() {
+        let mut durations = NoteDurations::default();
+        process(&mut durations, &[0x90, 60, 100], 1000);
+        process(&mut durations, &[0x90, 60, 100], 2000);
+        assert_eq!(
+            process(&mut durations, &[0x80, 60, 0], 3000),
+            Some(NoteDuration::Held(2000))
+        );
+        assert_eq!(
+            process(&mut durations, &[0x80, 60, 0], 4000),
+            Some(NoteDuration::Held(2000))
+        );
+        assert_eq!(
+            process(&mut durations, &[0x80, 60, 0], 5000),
+            Some(NoteDuration::Orphaned)
+        );
+    }
+
+    #[test]
+    fn held_notes() {
+        let mut held = HeldNotes::default();
+        let mut process =
+            |message: &[u8], timestamp| held.process(&MidiMessage::from_array(message), timestamp);
+
+        assert!(process(&[0x90, 60, 100], 1000));
+        assert!(!process(&[0x90, 60, 100], 2000));
+        assert!(process(&[0x91, 64, 100], 3000));
+        assert!(process(&[0x91, 67, 100], 4000));
+        assert!(process(&[0x80, 60, 0], 5000));
+        assert!(!process(&[0x80, 60, 0], 6000));
+        assert!(process(&[0x90, 72, 100], 7000));
+        assert!(!process(&[0xB1, 7, 100], 8000));
+
+        assert_eq!(
+            held.iter().collect::<Vec<_>>(),
+            vec![(0, 72, 7000), (1, 64, 3000), (1, 67, 4000)]
+        );
+        assert_eq!(held.notes(), BTreeSet::from([64, 67, 72]));
+
+        assert!(held.process(&MidiMessage::from_array(&[0xB1, 123, 0]), 9000));
+        assert_eq!(held.notes(), BTreeSet::from([72]));
+        assert!(held.process(&MidiMessage::from_array(&[0x90, 72, 0]), 9000));
+        assert!(held.notes().is_empty());
+    }
+
+    #[test]
+    fn note_watch() {
+        let mut watch = NoteWatch::new(Some(10_000_000), Some(2));
+        let mut process =
+            |message: &[u8], timestamp| watch.process(&MidiMessage::from_array(message), timestamp);
+
+        assert_eq!(process(&[0x90, 60, 100], 0), None);
+        assert_eq!(process(&[0x90, 64, 100], 1_000_000), None);
+        assert_eq!(process(&[0x90, 67, 100], 2_000_000), Some(3));
+        assert_eq!(process(&[0x90, 72, 100], 3_000_000), None);
+        assert_eq!(process(&[0x80, 72, 0], 4_000_000), None);
+        assert_eq!(process(&[0x80, 67, 0], 5_000_000), None);
+        assert_eq!(process(&[0x90, 67, 100], 6_000_000), Some(3));
+        assert_eq!(process(&[0x80, 67, 0], 7_000_000), None);
+
+        assert_eq!(watch.stuck_notes(10_000_000), vec![]);
+        assert_eq!(watch.stuck_notes(10_500_000), vec![(0, 60, 10_500_000)]);
+        assert_eq!(watch.stuck_notes(11_500_000), vec![(0, 64, 10_500_000)]);
+        assert_eq!(watch.stuck_notes(20_000_000), vec![]);
+
+        watch.process(&MidiMessage::from_array(&[0x80, 60, 0]), 21_000_000);
+        watch.process(&MidiMessage::from_array(&[0x90, 60, 100]), 22_000_000);
+        assert_eq!(watch.stuck_notes(32_500_000), vec![(0, 60, 10_500_000)]);
+    }
+
+    #[test]
+    fn mtc_sequence() {
+        let fields = [
+            MtcField::FramesLow,
+            MtcField::FramesHigh,
+            MtcField::SecondsLow,
+            MtcField::SecondsHigh,
+            MtcField::MinutesLow,
+            MtcField::MinutesHigh,
+            MtcField::HoursLow,
+            MtcField::HoursHighAndRate,
+        ];
+
+        let mut sequence = MtcSequence::default();
+        for field in fields.iter().chain(fields.iter()) {
+            assert_eq!(sequence.piece(*field), None);
+        }
+        for field in fields.iter().rev().skip(1) {
+            assert_eq!(sequence.piece(*field), None);
+        }
+
+        let mut sequence = MtcSequence::default();
+        assert_eq!(sequence.piece(MtcField::FramesLow), None);
+        assert_eq!(sequence.piece(MtcField::SecondsLow), Some(1));
+        assert_eq!(sequence.piece(MtcField::MinutesHigh), Some(2));
+        assert_eq!(sequence.piece(MtcField::MinutesHigh), Some(7));
+    }
+
+    #[test]
+    fn note_durations_orphaned() {
+        let mut durations = NoteDurations::default();
+        assert_eq!(
+            process(&mut durations, &[0x80, 64, 0], 1000),
+            Some(NoteDuration::Orphaned)
+        );
+        assert_eq!(process(&mut durations, &[0xB0, 64, 0], 1000), None);
+    }
+}
@@ -0,0 +1,1429 @@
+//! Formatting of messages for display
+
+use std::collections::BTreeSet;
+
+use crate::decode::ParameterKind;
+use crate::messages::{
+    drum_name, velocity_dynamic, MidiMessage, NoteNaming, Status, TimeSignature,
+};
+use crate::sysex;
+
+/// Display format options
+#[derive(Copy, Clone)]
+pub enum DisplayFormat {
+    Default,
+    Raw,
+    Min,
+    MinHex,
+    Json,
+    Csv,
+    Hexdump,
+}
+
+/// Display format of timestamps
+#[derive(Copy, Clone, Default)]
+pub enum TimestampFormat {
+    /// Seconds as provided by the MIDI driver
+    #[default]
+    Raw,
+    /// Time of day, timestamps are microseconds since midnight in local time
+    Wallclock,
+    /// Seconds since the previous message, timestamps are already differences
+    Delta,
+    /// No timestamps at all
+    None,
+}
+
+/// Options for the default display format
+#[derive(Copy, Clone, Default)]
+pub struct DisplayOptions {
+    pub note_naming: NoteNaming,
+    /// Show note numbers without note names
+    pub numeric_notes: bool,
+    /// Channel (0-15) to show General MIDI drum names instead of note names for
+    pub drum_channel: Option<u8>,
+    /// Pitch bend range in semitones to show bend values in semitones
+    pub bend_range: Option<f64>,
+    /// Show pitch bend values with sign and direction arrow, and mark the center
+    pub bend_direction: bool,
+    /// Reference frequency of A4 in Hz to show note frequencies
+    pub tuning: Option<f64>,
+    /// Show dynamic markings for note on velocities
+    pub dynamics: bool,
+    /// Show note on messages with velocity 0 as note off
+    pub implicit_note_off: bool,
+    /// Show status text in colors according to message type
+    pub color: bool,
+    /// Display format of timestamps
+    pub timestamp_format: TimestampFormat,
+    /// Show all message types in the same fixed-width columns
+    pub align: bool,
+    /// Show values of continuous controllers as bars
+    pub bars: bool,
+    /// Show General MIDI instrument names for program changes
+    pub gm: bool,
+    /// Show data bytes in hex instead of decimal
+    pub hex_data: bool,
+    /// Decode Roland and Yamaha parameter messages
+    pub pretty_sysex: bool,
+    /// Append the raw message bytes to lines in default format
+    pub with_raw: bool,
+    /// Verify the checksums of SysEx messages in recognized formats
+    pub verify_checksum: bool,
+    /// Show channels as 0-15 instead of 1-16
+    pub zero_based_channels: bool,
+    /// Combine the data bytes of pitch bend messages with the MSB first, against the specification
+    pub msb_first: bool,
+    /// Time signature to show song position pointers as bars and beats
+    pub song_position: Option<TimeSignature>,
+    /// Indent channel messages according to their channel
+    pub group_by_channel: bool,
+    /// Channel (0-15) shown for all channel messages instead of their own
+    pub assume_channel: Option<u8>,
+}
+
+/// Width of value bars in characters
+const BAR_WIDTH: usize = 16;
+
+/// Width of the pressure bars of poly key pressure messages in characters
+const POLY_BAR_WIDTH: usize = 8;
+
+/// Return a value from 0 to 127 as bar of block characters
+pub fn value_bar(value: u8, width: usize) -> String {
+    let filled = (value.min(127) as usize * width + 63) / 127;
+    format!("{}{}", "█".repeat(filled), "░".repeat(width - filled))
+}
+
+/// Return the value shown as bar for pressure, control change and pitch bend messages
+fn bar_value(msg: &MidiMessage, options: &DisplayOptions) -> Option<u8> {
+    match msg.status() {
+        Status::PolyKeyPressure | Status::ControlChange => msg.get_data(2),
+        Status::ChannelPressure => msg.get_data(1),
+        Status::PitchBend => data_u16(msg, options).map(|value| (value >> 7) as u8),
+        _ => None,
+    }
+}
+
+/// Return the 14-bit value of a message in the byte order given by the options
+fn data_u16(msg: &MidiMessage, options: &DisplayOptions) -> Option<u16> {
+    if options.msb_first {
+        msg.get_data_as_u16_msb_first()
+    } else {
+        msg.get_data_as_u16()
+    }
+}
+
+/// Format a timestamp in microseconds according to the timestamp format
+pub fn format_timestamp(timestamp: u64, format: TimestampFormat) -> String {
+    match format {
+        TimestampFormat::Raw | TimestampFormat::Delta => {
+            format!("{:10.6}", timestamp as f64 / 1e6)
+        }
+        TimestampFormat::Wallclock => {
+            let millis = timestamp / 1000;
+            let seconds = millis / 1000 % 86400;
+            format!(
+                "{:02}:{:02}:{:02}.{:03}",
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60,
+                millis % 1000
+            )
+        }
+        TimestampFormat::None => String::new(),
+    }
+}
+
+/// Return the shown number of a channel, 1-16 unless zero-based channels are enabled
+/// The channel is replaced by the assumed channel if one is set.
+pub fn channel_number(channel: u8, options: &DisplayOptions) -> u8 {
+    let channel = options.assume_channel.unwrap_or(channel);
+    if options.zero_based_channels {
+        channel
+    } else {
+        channel + 1
+    }
+}
+
+/// Number of spaces channel messages are indented per channel when grouped by channel
+const CHANNEL_INDENT: usize = 2;
+
+/// Return the indentation of a message if grouping by channel is enabled
+/// Channel messages are indented by their channel, system messages are not indented.
+fn channel_indent(msg: &MidiMessage, options: &DisplayOptions) -> String {
+    match msg.channel().filter(|_| options.group_by_channel) {
+        Some(channel) => " ".repeat(channel as usize * CHANNEL_INDENT),
+        None => String::new(),
+    }
+}
+
+/// Return the port and timestamp columns starting each line
+fn line_prefix(port: impl std::fmt::Display, timestamp: u64, options: &DisplayOptions) -> String {
+    match options.timestamp_format {
+        TimestampFormat::None => format!("  ({})", port),
+        format => format!("  ({})  {}", port, format_timestamp(timestamp, format)),
+    }
+}
+
+/// Return text wrapped in ANSI color codes according to the message type
+pub fn colorize(text: &str, status: Status) -> String {
+    let color = match status {
+        Status::NoteOff | Status::NoteOn | Status::PolyKeyPressure => "32",
+        Status::ControlChange => "33",
+        Status::PitchBend => "36",
+        Status::SystemExclusive
+        | Status::MtcQuarterFrame
+        | Status::SongPositionPointer
+        | Status::SongSelect
+        | Status::TuneRequest
+        | Status::EndOfExclusive => "35",
+        Status::TimingClock
+        | Status::Start
+        | Status::Continue
+        | Status::Stop
+        | Status::ActiveSensing
+        | Status::SystemReset => "90",
+        _ => return String::from(text),
+    };
+
+    format!("\x1b[{}m{}\x1b[0m", color, text)
+}
+
+/// Display message in default format
+pub fn display_default(
+    port: impl std::fmt::Display,
+    timestamp: u64,
+    message: &[u8],
+    options: &DisplayOptions,
+) -> String {
+    let msg = MidiMessage::from_array(message);
+
+    let implicit_note_off = options.implicit_note_off && msg.is_implicit_note_off();
+
+    let mut status_text = if implicit_note_off {
+        format!("{:21}", "Note Off (implicit)")
+    } else {
+        format!("{:21}", msg.status())
+    };
+    if options.color {
+        status_text = colorize(&status_text, msg.status());
+    }
+    status_text.insert_str(0, &channel_indent(&msg, options));
+
+    let data = |index| data_byte(&msg, index, options);
+    let channel = msg
+        .channel()
+        .map_or(0, |channel| channel_number(channel, options));
+    let drum_note = options.drum_channel.is_some() && options.drum_channel == msg.channel();
+    let mut note_name = if options.numeric_notes {
+        String::new()
+    } else if drum_note {
+        msg.get_data(1)
+            .and_then(drum_name)
+            .unwrap_or_default()
+            .to_string()
+    } else {
+        msg.note_name_with(&options.note_naming).unwrap_or_default()
+    };
+    if let Some(frequency) = options
+        .tuning
+        .filter(|_| !drum_note)
+        .and_then(|tuning| msg.note_frequency_with(tuning))
+    {
+        note_name = format!("{:4}  {:>7.2} Hz", note_name, frequency);
+    }
+
+    if options.align {
+        let line = aligned_row(
+            &line_prefix(&port, timestamp, options),
+            &status_text,
+            &msg,
+            &note_name,
+            implicit_note_off,
+            options,
+        );
+        return append_raw(line, message, options);
+    }
+
+    let mut data_text = match msg.status() {
+        Status::NoteOff | Status::NoteOn => {
+            let text = format!(
+                "Ch:{:>2}  Note:{:>3}  Vel:{:>3}    {}",
+                channel,
+                data(1),
+                data(2),
+                note_name
+            );
+            match dynamic_marking(&msg, implicit_note_off, options) {
+                Some(dynamic) => format!("{:32}  {}", text, dynamic),
+                None => text,
+            }
+        }
+        Status::PolyKeyPressure => format!(
+            "Ch:{:>2}  Note:{:>3}  Val:{:>3}  {}  {}",
+            channel,
+            data(1),
+            data(2),
+            msg.get_data(2).map_or_else(
+                || " ".repeat(POLY_BAR_WIDTH),
+                |value| value_bar(value, POLY_BAR_WIDTH)
+            ),
+            note_name
+        ),
+        Status::ControlChange => format!(
+            "Ch:{:>2}  No:  {:>3}  Val:{:>3}    {}",
+            channel,
+            data(1),
+            data(2),
+            cc_annotation(&msg)
+        ),
+        Status::ProgramChange if options.gm => format!(
+            "Ch:{:>2}  Val:{:>3}    {}",
+            channel,
+            data(1),
+            msg.program_name().unwrap_or_default()
+        ),
+        Status::ProgramChange | Status::ChannelPressure => {
+            format!("Ch:{:>2}  Val:{:>3}", channel, data(1))
+        }
+        Status::PitchBend => match data_u16(&msg, options) {
+            Some(value) => {
+                let value = value as i16 - 0x2000;
+                let mut text = format!("Ch:{:>2}  Val:{:>5}", channel, bend_value(value, options));
+                if let Some(direction) = bend_direction(value, options) {
+                    text.push_str(&format!(" {}", direction));
+                }
+                if let Some(semitones) = bend_semitones(value, options) {
+                    text.push_str(&format!("  {}", semitones));
+                }
+                text
+            }
+            None => format!("Ch:{:>2}  Val:{:>5}", channel, "-"),
+        },
+        Status::MtcQuarterFrame => match msg.mtc_piece() {
+            Some((field, value)) => format!("{}: {}", field, value),
+            None => String::from("-"),
+        },
+        Status::SongSelect => format!("{:>3}", data(1)),
+        Status::SongPositionPointer => song_position(&msg, options)
+            .unwrap_or_else(|| format!("{:>3}  {:>3}", data(1), data(2))),
+        Status::SystemExclusive => sysex_annotation(&msg, options),
+        Status::TuneRequest
+        | Status::TimingClock
+        | Status::Start
+        | Status::Continue
+        | Status::Stop
+        | Status::ActiveSensing
+        | Status::SystemReset => String::new(),
+        // Terminators of SysEx messages are part of the reassembled message, so this one is stray
+        Status::EndOfExclusive => String::from("(no SysEx in progress)"),
+        _ => format!("{:?}", msg.data),
+    };
+
+    // Poly pressure always has its own bar next to the value
+    if let Some(value) =
+        bar_value(&msg, options).filter(|_| options.bars && msg.status() != Status::PolyKeyPressure)
+    {
+        data_text = format!("{}  {}", data_text, value_bar(value, BAR_WIDTH));
+    }
+
+    let line = format!(
+        "{}  {}  {}",
+        line_prefix(&port, timestamp, options),
+        status_text,
+        data_text
+    );
+    append_raw(line, message, options)
+}
+
+/// Return a line with the raw message bytes appended if enabled
+fn append_raw(line: String, message: &[u8], options: &DisplayOptions) -> String {
+    if options.with_raw {
+        format!("{}  {}", line, raw_bytes(message))
+    } else {
+        line
+    }
+}
+
+/// Return the message bytes as shown in raw format
+fn raw_bytes(message: &[u8]) -> String {
+    format!("{:?}", message)
+}
+
+/// Return a data byte in decimal or hex
+/// Missing data bytes of short messages are shown as placeholders
+fn data_byte(msg: &MidiMessage, index: usize, options: &DisplayOptions) -> String {
+    match msg.get_data(index) {
+        Some(byte) if options.hex_data => format!("0x{:02X}", byte),
+        Some(byte) => byte.to_string(),
+        None => String::from("-"),
+    }
+}
+
+/// Return message data in the fixed-width columns channel, data 1, data 2 and annotation
+fn aligned_row(
+    prefix: &str,
+    status_text: &str,
+    msg: &MidiMessage,
+    note_name: &str,
+    implicit_note_off: bool,
+    options: &DisplayOptions,
+) -> String {
+    let data = |index| data_byte(msg, index, options);
+
+    let (data1, data2, mut annotation) = match msg.status() {
+        Status::NoteOff | Status::NoteOn => (
+            data(1),
+            data(2),
+            match dynamic_marking(msg, implicit_note_off, options) {
+                Some(dynamic) => format!("{}  {}", note_name, dynamic),
+                None => String::from(note_name),
+            },
+        ),
+        Status::PolyKeyPressure => (data(1), data(2), String::from(note_name)),
+        Status::ControlChange => (data(1), data(2), cc_annotation(msg)),
+        Status::ProgramChange if options.gm => (
+            data(1),
+            String::new(),
+            msg.program_name().unwrap_or_default(),
+        ),
+        Status::ProgramChange | Status::ChannelPressure | Status::SongSelect => {
+            (data(1), String::new(), String::new())
+        }
+        Status::PitchBend => match data_u16(msg, options) {
+            Some(value) => {
+                let value = value as i16 - 0x2000;
+                let annotation = [
+                    bend_direction(value, options),
+                    bend_semitones(value, options),
+                ];
+                (
+                    bend_value(value, options),
+                    String::new(),
+                    annotation
+                        .into_iter()
+                        .flatten()
+                        .collect::<Vec<_>>()
+                        .join("  "),
+                )
+            }
+            None => (String::from("-"), String::new(), String::new()),
+        },
+        Status::MtcQuarterFrame => (
+            data(1),
+            String::new(),
+            msg.mtc_piece().map_or(String::new(), |(field, value)| {
+                format!("{}: {}", field, value)
+            }),
+        ),
+        Status::SongPositionPointer => (
+            data(1),
+            data(2),
+            song_position(msg, options).unwrap_or_default(),
+        ),
+        Status::SystemExclusive => (String::new(), String::new(), sysex_annotation(msg, options)),
+        Status::EndOfExclusive => (
+            String::new(),
+            String::new(),
+            String::from("(no SysEx in progress)"),
+        ),
+        Status::Error => (String::new(), String::new(), format!("{:?}", msg.data)),
+        _ => (String::new(), String::new(), String::new()),
+    };
+
+    if let Some(value) = bar_value(msg, options).filter(|_| options.bars) {
+        annotation = format!("{}  {}", value_bar(value, BAR_WIDTH), annotation);
+    }
+
+    let channel = msg.channel().map_or(String::new(), |channel| {
+        channel_number(channel, options).to_string()
+    });
+
+    let line = format!(
+        "{}  {}  {:>2}  {:>5}  {:>5}  {}",
+        prefix, status_text, channel, data1, data2, annotation
+    );
+    String::from(line.trim_end())
+}
+
+/// Return dynamic marking of note on messages if enabled
+fn dynamic_marking(
+    msg: &MidiMessage,
+    implicit_note_off: bool,
+    options: &DisplayOptions,
+) -> Option<&'static str> {
+    match msg.get_data(2) {
+        Some(velocity)
+            if options.dynamics && matches!(msg.status(), Status::NoteOn) && !implicit_note_off =>
+        {
+            Some(velocity_dynamic(velocity))
+        }
+        _ => None,
+    }
+}
+
+/// Return controller name and the meaning of channel mode values
+fn cc_annotation(msg: &MidiMessage) -> String {
+    let name = msg.cc_name().unwrap_or_default();
+    match (msg.get_data(1), msg.get_data(2)) {
+        (Some(controller), Some(value)) => match channel_mode_value(controller, value) {
+            Some(mode) => format!("{}: {}", name, mode),
+            None => name,
+        },
+        _ => name,
+    }
+}
+
+/// Return a signed pitch bend value, with explicit plus sign if direction display is enabled
+fn bend_value(value: i16, options: &DisplayOptions) -> String {
+    if options.bend_direction && value > 0 {
+        format!("{:+}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+/// Return the direction of a signed pitch bend value as arrow or the center marker if enabled
+fn bend_direction(value: i16, options: &DisplayOptions) -> Option<String> {
+    if !options.bend_direction {
+        return None;
+    }
+
+    let direction = match value.signum() {
+        1 => "↑",
+        -1 => "↓",
+        _ => "(CENTER)",
+    };
+    Some(String::from(direction))
+}
+
+/// Return the position of a song position pointer in sixteenths and as bar, beat and sixteenth
+/// Returns `None` unless song positions are decoded or if the message is too short
+fn song_position(msg: &MidiMessage, options: &DisplayOptions) -> Option<String> {
+    let signature = options.song_position?;
+    let sixteenths = match msg.status() {
+        Status::SongPositionPointer => data_u16(msg, options)?,
+        _ => return None,
+    };
+    let (bar, beat, sixteenth) = signature.position(sixteenths);
+
+    Some(format!(
+        "Pos:{:>6}  Bar: {}:{}:{}",
+        sixteenths, bar, beat, sixteenth
+    ))
+}
+
+/// Return pitch bend value in semitones if a bend range is set
+fn bend_semitones(value: i16, options: &DisplayOptions) -> Option<String> {
+    options
+        .bend_range
+        .map(|range| format!("{:+.2} st", value as f64 / 8192.0 * range))
+}
+
+/// Return decoded universal SysEx content or manufacturer and size
+/// Roland and Yamaha parameter messages are decoded if enabled, checksums are verified if enabled
+fn sysex_annotation(msg: &MidiMessage, options: &DisplayOptions) -> String {
+    let text = match sysex::decode(&msg.data).filter(|_| options.pretty_sysex) {
+        Some(text) => text,
+        None => match (msg.universal_sysex(), msg.manufacturer_name()) {
+            (Some((device, content)), _) => format!("{}  Dev: {}", content, device),
+            (None, Some(name)) => format!("{}  ({} bytes)", name, msg.data.len()),
+            (None, None) => format!("{:?}", msg.data),
+        },
+    };
+
+    match sysex::verify_checksum(&msg.data).filter(|_| options.verify_checksum) {
+        Some(check) => format!("{}  {}", text, check),
+        None => text,
+    }
+}
+
+/// Return the meaning of the value of a channel mode message
+fn channel_mode_value(controller: u8, value: u8) -> Option<String> {
+    match (controller, value) {
+        (122, 0) => Some(String::from("Off")),
+        (122, 127) => Some(String::from("On")),
+        (126, 0) => Some(String::from("all channels")),
+        (126, 1) => Some(String::from("1 channel")),
+        (126, _) => Some(format!("{} channels", value)),
+        _ => None,
+    }
+}
+
+/// Display message not matching the length or data required by its status in default format
+pub fn display_malformed(
+    port: impl std::fmt::Display,
+    timestamp: u64,
+    message: &[u8],
+    options: &DisplayOptions,
+) -> String {
+    let msg = MidiMessage::from_array(message);
+
+    let mut status_text = format!("{:21}", "Malformed");
+    if options.color {
+        status_text = format!("\x1b[31m{}\x1b[0m", status_text);
+    }
+
+    format!(
+        "{}  {}  {}  {:?}",
+        line_prefix(&port, timestamp, options),
+        status_text,
+        msg.status(),
+        message
+    )
+}
+
+/// Display decoded RPN or NRPN data entry in default format
+pub fn display_parameter(
+    port: impl std::fmt::Display,
+    timestamp: u64,
+    channel: u8,
+    kind: ParameterKind,
+    number: u16,
+    value: u16,
+    options: &DisplayOptions,
+) -> String {
+    let mut status_text = format!(
+        "{:21}",
+        match kind {
+            ParameterKind::Registered => "RPN",
+            ParameterKind::NonRegistered => "NRPN",
+        }
+    );
+    if options.color {
+        status_text = colorize(&status_text, Status::ControlChange);
+    }
+
+    format!(
+        "{}  {}  Ch:{:>2}  {} = {}",
+        line_prefix(&port, timestamp, options),
+        status_text,
+        channel_number(channel, options),
+        number,
+        value
+    )
+}
+
+/// Display combined 14-bit control change in default format
+pub fn display_cc14(
+    port: impl std::fmt::Display,
+    timestamp: u64,
+    channel: u8,
+    controller: u8,
+    value: u16,
+    options: &DisplayOptions,
+) -> String {
+    let mut status_text = format!("{:21}", "Control Change 14-bit");
+    if options.color {
+        status_text = colorize(&status_text, Status::ControlChange);
+    }
+
+    let name = MidiMessage::from_array(&[Status::ControlChange as u8 | channel, controller, 0])
+        .cc_name()
+        .unwrap();
+
+    format!(
+        "{}  {}  Ch:{:>2}  No:  {:>3}  Val:{:>5}  {}",
+        line_prefix(&port, timestamp, options),
+        status_text,
+        channel_number(channel, options),
+        controller,
+        value,
+        name
+    )
+}
+
+/// Display message in raw format
+pub fn display_raw(
+    port: impl std::fmt::Display,
+    timestamp: u64,
+    message: &[u8],
+    options: &DisplayOptions,
+) -> String {
+    format!(
+        "{}   {}",
+        line_prefix(&port, timestamp, options),
+        raw_bytes(message)
+    )
+}
+
+/// Display message in min format
+pub fn display_min(message: &[u8]) -> String {
+    let mut msg = Vec::<String>::new();
+
+    for byte in message {
+        msg.push(format!("{}", byte));
+    }
+
+    msg.join(", ")
+}
+
+/// Formatting of the bytes in min hex format
+#[derive(Clone)]
+pub struct HexFormat {
+    /// Text in front of each byte
+    pub prefix: String,
+    /// Text between the bytes
+    pub separator: String,
+    /// Show the hex digits A-F in uppercase
+    pub uppercase: bool,
+}
+
+impl Default for HexFormat {
+    fn default() -> Self {
+        Self {
+            prefix: String::from("0x"),
+            separator: String::from(", "),
+            uppercase: true,
+        }
+    }
+}
+
+/// Display message in min hex format
+pub fn display_min_hex(message: &[u8], format: &HexFormat) -> String {
+    let mut msg = Vec::<String>::new();
+
+    for byte in message {
+        if format.uppercase {
+            msg.push(format!("{}{:02X}", format.prefix, byte));
+        } else {
+            msg.push(format!("{}{:02x}", format.prefix, byte));
+        }
+    }
+
+    msg.join(&format.separator)
+}
+
+/// Return a comment with the message type to append to lines in min and min hex format
+pub fn min_annotation(message: &[u8]) -> String {
+    format!("  # {}", MidiMessage::from_array(message).status())
+}
+
+/// Return a line with a byte listing shortened to a maximum width in characters
+/// The listing is cut at the last separator that fits, followed by an ellipsis and the number of
+/// bytes left out. Lines that fit or contain no listing are returned unchanged.
+pub fn truncate_byte_listing(line: &str, max_width: usize, separator: &str) -> String {
+    if line.chars().count() <= max_width || separator.is_empty() {
+        return String::from(line);
+    }
+
+    let separators = line.matches(separator).count();
+    let mut cut = None;
+    let mut width = 0;
+    let mut previous = 0;
+
+    for (kept, (index, _)) in line.match_indices(separator).enumerate() {
+        width += line[previous..index].chars().count();
+        previous = index;
+        let suffix = format!("{}… (+{} more bytes)", separator, separators - kept);
+        if cut.is_some() && width + suffix.chars().count() > max_width {
+            break;
+        }
+        cut = Some((index, suffix));
+    }
+
+    match cut {
+        Some((index, suffix)) => format!("{}{}", &line[..index], suffix),
+        None => String::from(line),
+    }
+}
+
+/// Minimum number of octaves shown by the keyboard
+pub const KEYBOARD_OCTAVES: u8 = 2;
+
+/// Return the lowest octave of the keyboard for the held notes
+/// The previous octave is kept while it shows all notes or if no notes are held
+pub fn keyboard_octave(notes: &BTreeSet<u8>, previous: u8) -> u8 {
+    match (notes.first(), notes.last()) {
+        (Some(&lowest), Some(&highest))
+            if lowest / 12 < previous || highest / 12 >= previous + KEYBOARD_OCTAVES =>
+        {
+            lowest / 12
+        }
+        _ => previous,
+    }
+}
+
+/// Return a single-line keyboard starting at the given octave with held notes marked as `*`
+/// At least KEYBOARD_OCTAVES octaves are shown, more if held notes are above them.
+/// White keys are shown as `-`, black keys as `=`, octaves are separated by `|`.
+pub fn display_keyboard(notes: &BTreeSet<u8>, octave: u8, naming: &NoteNaming) -> String {
+    let highest_octave = notes
+        .last()
+        .map_or(0, |note| note / 12)
+        .max(octave + KEYBOARD_OCTAVES - 1)
+        .min(10);
+    let name = |note: u8| {
+        MidiMessage::from_array(&[Status::NoteOn as u8, note, 127])
+            .note_name_with(naming)
+            .unwrap_or_default()
+    };
+
+    let mut keys = String::from("|");
+    for note in octave * 12..=(highest_octave * 12 + 11).min(127) {
+        keys.push(if notes.contains(&note) {
+            '*'
+        } else if matches!(note % 12, 1 | 3 | 6 | 8 | 10) {
+            '='
+        } else {
+            '-'
+        });
+        if note % 12 == 11 || note == 127 {
+            keys.push('|');
+        }
+    }
+
+    format!(
+        "{:>4} {} {}",
+        name(octave * 12),
+        keys,
+        name((highest_octave * 12 + 11).min(127))
+    )
+}
+
+/// Display message as hexdump with 16 bytes per row, offset and ASCII column
+pub fn display_hexdump(message: &[u8]) -> String {
+    let mut rows = Vec::new();
+
+    for (row, chunk) in message.chunks(16).enumerate() {
+        let mut hex = String::new();
+        for (i, byte) in chunk.iter().enumerate() {
+            if i == 8 {
+                hex.push(' ');
+            }
+            hex.push_str(&format!("{:02X} ", byte));
+        }
+
+        let ascii: String = chunk
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+
+        rows.push(format!("{:08X}  {:49} |{}|", row * 16, hex, ascii));
+    }
+
+    rows.join("\n")
+}
+
+/// Display message in JSON format, one object per line
+pub fn display_json(
+    port_id: usize,
+    timestamp: u64,
+    message: &[u8],
+    options: &DisplayOptions,
+) -> String {
+    let msg = MidiMessage::from_array(message);
+
+    let mut fields = vec![
+        format!("\"port\":{}", port_id),
+        format!("\"timestamp\":{}", timestamp),
+        format!("\"status\":\"{}\"", msg.status()),
+        format!(
+            "\"channel\":{}",
+            match msg.channel() {
+                Some(channel) => channel_number(channel, options).to_string(),
+                None => String::from("null"),
+            }
+        ),
+    ];
+
+    if !msg.is_valid() {
+        fields.push(String::from("\"malformed\":true"));
+    }
+
+    match msg.status() {
+        _ if !msg.is_valid() => (),
+        Status::NoteOff | Status::NoteOn => {
+            fields.push(format!("\"note\":{}", msg.data(1)));
+            fields.push(format!("\"velocity\":{}", msg.data(2)));
+        }
+        Status::PolyKeyPressure => {
+            fields.push(format!("\"note\":{}", msg.data(1)));
+            fields.push(format!("\"value\":{}", msg.data(2)));
+        }
+        Status::ControlChange => {
+            fields.push(format!("\"controller\":{}", msg.data(1)));
+            fields.push(format!("\"value\":{}", msg.data(2)));
+        }
+        Status::ProgramChange
+        | Status::ChannelPressure
+        | Status::MtcQuarterFrame
+        | Status::SongSelect => {
+            fields.push(format!("\"value\":{}", msg.data(1)));
+        }
+        Status::PitchBend => {
+            let value = data_u16(&msg, options).unwrap_or(0x2000);
+            fields.push(format!("\"value\":{}", value as i16 - 0x2000));
+        }
+        Status::SongPositionPointer => {
+            let value = data_u16(&msg, options).unwrap_or(0);
+            fields.push(format!("\"value\":{}", value));
+        }
+        _ => (),
+    }
+
+    let raw: Vec<String> = message.iter().map(|byte| byte.to_string()).collect();
+    fields.push(format!("\"raw\":[{}]", raw.join(",")));
+
+    format!("{{{}}}", fields.join(","))
+}
+
+/// Display message according to a template with placeholders in braces
+/// Supported placeholders are port, ts, status, ch, d1, d2, note and raw.
+/// Unknown placeholders are kept as they are, `{{` and `}}` are shown as single braces.
+pub fn display_template(
+    template: &str,
+    port: impl std::fmt::Display,
+    timestamp: u64,
+    message: &[u8],
+    options: &DisplayOptions,
+) -> String {
+    let msg = MidiMessage::from_array(message);
+
+    let mut line = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                line.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                line.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    name.push(c);
+                }
+                let value = match name.as_str() {
+                    _ if !closed => None,
+                    "port" => Some(port.to_string()),
+                    "ts" => Some(
+                        format_timestamp(timestamp, options.timestamp_format)
+                            .trim_start()
+                            .to_string(),
+                    ),
+                    "status" => Some(msg.status().to_string()),
+                    "ch" => Some(msg.channel().map_or(String::new(), |channel| {
+                        channel_number(channel, options).to_string()
+                    })),
+                    "d1" => Some(msg.get_data(1).map_or(String::new(), |d| d.to_string())),
+                    "d2" => Some(msg.get_data(2).map_or(String::new(), |d| d.to_string())),
+                    "note" => Some(msg.note_name_with(&options.note_naming).unwrap_or_default()),
+                    "raw" => Some(display_min(message)),
+                    _ => None,
+                };
+                match value {
+                    Some(value) => line.push_str(&value),
+                    None => {
+                        line.push('{');
+                        line.push_str(&name);
+                        if closed {
+                            line.push('}');
+                        }
+                    }
+                }
+            }
+            c => line.push(c),
+        }
+    }
+
+    line
+}
+
+/// Return header row for the CSV format
+pub fn csv_header() -> &'static str {
+    "timestamp,port,status,channel,data1,data2"
+}
+
+/// Display message in CSV format
+pub fn display_csv(
+    port_id: usize,
+    timestamp: u64,
+    message: &[u8],
+    options: &DisplayOptions,
+) -> String {
+    let msg = MidiMessage::from_array(message);
+
+    let channel = msg.channel().map_or(String::new(), |channel| {
+        channel_number(channel, options).to_string()
+    });
+
+    let data = |index| match msg.status() {
+        Status::SystemExclusive => String::new(),
+        _ => msg
+            .get_data(index)
+            .map_or(String::new(), |byte| byte.to_string()),
+    };
+
+    format!(
+        "{:.6},{},{},{},{},{}",
+        timestamp as f64 / 1e6,
+        port_id,
+        msg.status(),
+        channel,
+        data(1),
+        data(2)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_long_listing() {
+        let message: Vec<u8> = (0..100).collect();
+        let line = display_min(&message);
+        assert_eq!(truncate_byte_listing(&line, line.len(), ", "), line);
+
+        let truncated = truncate_byte_listing(&line, 40, ", ");
+        assert_eq!(truncated, "0, 1, 2, 3, 4, 5, 6, … (+93 more bytes)");
+        assert!(truncated.chars().count() <= 40);
+
+        assert_eq!(
+            truncate_byte_listing(&line, 5, ", "),
+            "0, … (+99 more bytes)"
+        );
+        assert_eq!(
+            truncate_byte_listing("no listing here", 5, ", "),
+            "no listing here"
+        );
+
+        let format = HexFormat {
+            prefix: String::new(),
+            separator: String::from(" "),
+            uppercase: true,
+        };
+        assert_eq!(
+            truncate_byte_listing(&display_min_hex(&message, &format), 30, " "),
+            "00 01 02 03 … (+96 more bytes)"
+        );
+    }
+
+    #[test]
+    fn min_hex_formats() {
+        let message = [0x90, 0x3C, 0x7F];
+        assert_eq!(
+            display_min_hex(&message, &HexFormat::default()),
+            "0x90, 0x3C, 0x7F"
+        );
+
+        let format = HexFormat {
+            prefix: String::new(),
+            separator: String::from(" "),
+            uppercase: true,
+        };
+        assert_eq!(display_min_hex(&message, &format), "90 3C 7F");
+
+        let format = HexFormat {
+            uppercase: false,
+            ..Default::default()
+        };
+        assert_eq!(display_min_hex(&message, &format), "0x90, 0x3c, 0x7f");
+    }
+
+    #[test]
+    fn display_default_short_messages() {
+        let options = DisplayOptions {
+            dynamics: true,
+            bend_range: Some(2.0),
+            ..Default::default()
+        };
+
+        for status in [0x80, 0x90, 0xA0, 0xB0, 0xC0, 0xD0, 0xE0, 0xF1, 0xF2, 0xF3] {
+            let line = display_default(0, 0, &[status], &options);
+            assert!(line.contains('-'), "{}", line);
+            display_default(0, 0, &[status, 60], &options);
+        }
+    }
+
+    #[test]
+    fn display_default_msb_first() {
+        let options = DisplayOptions {
+            msb_first: true,
+            ..Default::default()
+        };
+        let line = display_default(0, 0, &[0xE0, 0x40, 0x01], &options);
+        assert!(line.ends_with("Val:    1"), "{}", line);
+
+        let line = display_json(0, 0, &[0xE0, 0x40, 0x01], &options);
+        assert!(line.contains("\"value\":1,"), "{}", line);
+
+        let line = display_json(0, 0, &[0xF2, 0x00, 0x08], &options);
+        assert!(line.contains("\"value\":8,"), "{}", line);
+
+        let options = DisplayOptions {
+            msb_first: true,
+            song_position: Some(TimeSignature::default()),
+            ..Default::default()
+        };
+        let line = display_default(0, 0, &[0xF2, 0x00, 0x11], &options);
+        assert!(line.ends_with("Pos:    17  Bar: 2:1:2"), "{}", line);
+    }
+
+    #[test]
+    fn display_default_bend_direction() {
+        let options = DisplayOptions {
+            bend_direction: true,
+            ..Default::default()
+        };
+
+        let line = display_default(0, 0, &[0xE0, 0x52, 0x49], &options);
+        assert!(line.ends_with("Val:+1234 ↑"), "{}", line);
+        let line = display_default(0, 0, &[0xE0, 0x00, 0x40], &options);
+        assert!(line.ends_with("Val:    0 (CENTER)"), "{}", line);
+        let line = display_default(0, 0, &[0xE0, 0x00, 0x00], &options);
+        assert!(line.ends_with("Val:-8192 ↓"), "{}", line);
+    }
+
+    #[test]
+    fn min_annotations() {
+        assert_eq!(min_annotation(&[0xA0, 60, 10]), "  # Poly Key Pressure");
+        assert_eq!(min_annotation(&[0xD0, 10]), "  # Channel Pressure");
+    }
+
+    #[test]
+    fn display_default_zero_based_channels() {
+        let options = DisplayOptions {
+            zero_based_channels: true,
+            ..Default::default()
+        };
+        let line = display_default(0, 0, &[0xC9, 5], &options);
+        assert!(line.ends_with("Ch: 9  Val:  5"), "{}", line);
+
+        let line = display_template("{ch}", 0, 0, &[0xC9, 5], &options);
+        assert_eq!(line, "9");
+
+        let line = display_json(0, 0, &[0xC9, 5], &options);
+        assert!(line.contains("\"channel\":9,"), "{}", line);
+
+        let line = display_csv(0, 0, &[0xC9, 5], &options);
+        assert!(line.contains(",9,"), "{}", line);
+    }
+
+    #[test]
+    fn display_default_port_label() {
+        let options = DisplayOptions {
+            timestamp_format: TimestampFormat::None,
+            ..Default::default()
+        };
+        let line = display_default("1 Keys", 0, &[0xF8], &options);
+        assert!(line.starts_with("  (1 Keys)  Timing Clock"), "{}", line);
+    }
+
+    #[test]
+    fn display_default_end_of_exclusive() {
+        let line = display_default(0, 0, &[0xF7], &DisplayOptions::default());
+        assert!(
+            line.ends_with("End of Exclusive       (no SysEx in progress)"),
+            "{}",
+            line
+        );
+    }
+
+    #[test]
+    fn display_default_song_position() {
+        let message = [0xF2, 0x24, 0x00];
+        let line = display_default(0, 0, &message, &DisplayOptions::default());
+        assert!(
+            line.ends_with("Song Position Pointer   36    0"),
+            "{}",
+            line
+        );
+
+        let options = DisplayOptions {
+            song_position: Some(TimeSignature::default()),
+            ..Default::default()
+        };
+        let line = display_default(0, 0, &message, &options);
+        assert!(line.ends_with("Pos:    36  Bar: 3:2:1"), "{}", line);
+    }
+
+    #[test]
+    fn display_default_group_by_channel() {
+        let options = DisplayOptions {
+            timestamp_format: TimestampFormat::None,
+            group_by_channel: true,
+            ..Default::default()
+        };
+
+        let line = display_default(0, 0, &[0x90, 60, 100], &options);
+        assert!(line.starts_with("  (0)  Note On"), "{}", line);
+        let line = display_default(0, 0, &[0x92, 60, 100], &options);
+        assert!(line.starts_with("  (0)      Note On"), "{}", line);
+        let line = display_default(0, 0, &[0xF8], &options);
+        assert!(line.starts_with("  (0)  Timing Clock"), "{}", line);
+    }
+
+    #[test]
+    fn display_default_poly_pressure() {
+        let options = DisplayOptions::default();
+
+        let line = display_default(0, 0, &[0xA0, 60, 64], &options);
+        assert!(
+            line.ends_with("Ch: 1  Note: 60  Val: 64  ████░░░░  C3"),
+            "{}",
+            line
+        );
+        let line = display_default(0, 0, &[0xA0, 60, 127], &options);
+        assert!(line.contains("Val:127  ████████  C3"), "{}", line);
+
+        let options = DisplayOptions {
+            bars: true,
+            ..Default::default()
+        };
+        let line = display_default(0, 0, &[0xA0, 60, 0], &options);
+        assert!(line.ends_with("Val:  0  ░░░░░░░░  C3"), "{}", line);
+    }
+
+    #[test]
+    fn display_default_assume_channel() {
+        let options = DisplayOptions {
+            assume_channel: Some(9),
+            ..Default::default()
+        };
+
+        let line = display_default(0, 0, &[0x92, 60, 100], &options);
+        assert!(line.contains("Ch:10  Note: 60"), "{}", line);
+        let line = display_default(0, 0, &[0xB0, 7, 100], &options);
+        assert!(line.contains("Ch:10  No:    7"), "{}", line);
+    }
+
+    #[test]
+    fn display_default_empty_message() {
+        let line = display_default(0, 0, &[], &DisplayOptions::default());
+        assert!(line.contains("Error or unknown"));
+    }
+
+    #[test]
+    fn display_default_note_without_velocity() {
+        let line = display_default(0, 0, &[0x90, 60], &DisplayOptions::default());
+        assert!(line.contains("Note: 60  Vel:  -"));
+        assert!(line.contains("C3"));
+    }
+
+    #[test]
+    fn display_default_channel_mode() {
+        let options = DisplayOptions::default();
+
+        let line = display_default(0, 0, &[0xB0, 122, 0], &options);
+        assert!(line.ends_with("Local Control: Off"), "{}", line);
+        let line = display_default(0, 0, &[0xB0, 122, 127], &options);
+        assert!(line.ends_with("Local Control: On"), "{}", line);
+        let line = display_default(0, 0, &[0xB0, 126, 4], &options);
+        assert!(line.ends_with("Mono Mode: 4 channels"), "{}", line);
+        let line = display_default(0, 0, &[0xB0, 126, 0], &options);
+        assert!(line.ends_with("Mono Mode: all channels"), "{}", line);
+        let line = display_default(0, 0, &[0xB0, 123, 0], &options);
+        assert!(line.ends_with("All Notes Off"), "{}", line);
+    }
+
+    #[test]
+    fn display_default_note_names() {
+        let options = DisplayOptions {
+            numeric_notes: true,
+            ..Default::default()
+        };
+        let line = display_default(0, 0, &[0x90, 60, 100], &options);
+        assert!(line.ends_with("Vel:100    "), "{}", line);
+
+        let options = DisplayOptions {
+            drum_channel: Some(9),
+            ..Default::default()
+        };
+        let line = display_default(0, 0, &[0x99, 38, 100], &options);
+        assert!(line.ends_with("Acoustic Snare"), "{}", line);
+        let line = display_default(0, 0, &[0x90, 38, 100], &options);
+        assert!(line.ends_with("D1"), "{}", line);
+    }
+
+    #[test]
+    fn display_default_aligned() {
+        let options = DisplayOptions {
+            align: true,
+            ..Default::default()
+        };
+
+        let note = display_default(0, 0, &[0x90, 60, 100], &options);
+        let cc = display_default(0, 0, &[0xB1, 7, 90], &options);
+        let pc = display_default(0, 0, &[0xC2, 5], &options);
+        let clock = display_default(0, 0, &[0xF8], &options);
+
+        assert!(note.ends_with("Note On                 1     60    100  C3"));
+        assert!(cc.ends_with("Control Change          2      7     90  Volume"));
+        assert!(pc.ends_with("Program Change          3      5"));
+        assert!(clock.ends_with("Timing Clock"));
+        assert_eq!(note.find("C3"), cc.find("Volume"));
+    }
+
+    #[test]
+    fn display_default_hex_data() {
+        let options = DisplayOptions {
+            hex_data: true,
+            ..Default::default()
+        };
+
+        let line = display_default(0, 0, &[0x90, 60, 100], &options);
+        assert!(line.contains("Note:0x3C  Vel:0x64"), "{}", line);
+        assert!(line.ends_with("C3"), "{}", line);
+        let line = display_default(0, 0, &[0xB0, 7, 127], &options);
+        assert!(line.contains("No:  0x07  Val:0x7F"), "{}", line);
+        assert!(line.ends_with("Volume"), "{}", line);
+        let line = display_default(0, 0, &[0xC0], &options);
+        assert!(line.ends_with("Val:  -"), "{}", line);
+    }
+
+    #[test]
+    fn display_default_with_raw() {
+        let options = DisplayOptions {
+            with_raw: true,
+            ..Default::default()
+        };
+        let line = display_default(0, 0, &[0xB0, 7, 127], &options);
+        assert!(line.ends_with("Volume  [176, 7, 127]"), "{}", line);
+
+        let options = DisplayOptions {
+            with_raw: true,
+            align: true,
+            ..Default::default()
+        };
+        let line = display_default(0, 0, &[0xF8], &options);
+        assert!(line.ends_with("Timing Clock  [248]"), "{}", line);
+    }
+
+    #[test]
+    fn display_default_verify_checksum() {
+        let options = DisplayOptions {
+            verify_checksum: true,
+            ..Default::default()
+        };
+        let message = [
+            0xF0, 0x41, 0x10, 0x42, 0x12, 0x40, 0x00, 0x7F, 0x00, 0x40, 0xF7,
+        ];
+        let line = display_default(0, 0, &message, &options);
+        assert!(
+            line.ends_with("(11 bytes)  [checksum BAD: got 40 expected 41]"),
+            "{}",
+            line
+        );
+    }
+
+    #[test]
+    fn value_bars() {
+        assert_eq!(value_bar(0, 8), "░░░░░░░░");
+        assert_eq!(value_bar(64, 8), "████░░░░");
+        assert_eq!(value_bar(127, 8), "████████");
+
+        let options = DisplayOptions {
+            bars: true,
+            ..Default::default()
+        };
+        let line = display_default(0, 0, &[0xD0, 127], &options);
+        assert!(line.ends_with(&value_bar(127, BAR_WIDTH)), "{}", line);
+        let line = display_default(0, 0, &[0xE0, 0, 64], &options);
+        assert!(line.ends_with(&value_bar(64, BAR_WIDTH)), "{}", line);
+        let line = display_default(0, 0, &[0x90, 60, 100], &options);
+        assert!(!line.contains('█'), "{}", line);
+    }
+
+    #[test]
+    fn keyboard() {
+        let naming = NoteNaming::default();
+        let notes = BTreeSet::from([60, 64, 67]);
+
+        assert_eq!(
+            display_keyboard(&notes, 5, &naming),
+            "  C3 |*=-=*-=*=-=-|-=-=--=-=-=-| B4"
+        );
+        assert_eq!(
+            display_keyboard(&BTreeSet::from([60, 84]), 5, &naming),
+            "  C3 |*=-=--=-=-=-|-=-=--=-=-=-|*=-=--=-=-=-| B5"
+        );
+        assert_eq!(
+            display_keyboard(&BTreeSet::from([127]), 9, &naming),
+            "  C7 |-=-=--=-=-=-|-=-=--=*| G8"
+        );
+
+        assert_eq!(keyboard_octave(&notes, 5), 5);
+        assert_eq!(keyboard_octave(&notes, 4), 4);
+        assert_eq!(keyboard_octave(&notes, 3), 5);
+        assert_eq!(keyboard_octave(&BTreeSet::from([40]), 5), 3);
+        assert_eq!(keyboard_octave(&BTreeSet::new(), 3), 3);
+    }
+
+    #[test]
+    fn hexdump() {
+        let mut message = vec![0xF0, 0x41];
+        message.extend_from_slice(b"Hello, World!");
+        message.extend_from_slice(&[0x00, 0x7F, 0xF7]);
+
+        assert_eq!(
+            display_hexdump(&message),
+            "00000000  F0 41 48 65 6C 6C 6F 2C  20 57 6F 72 6C 64 21 00  |.AHello, World!.|\n\
+             00000010  7F F7                                             |..|"
+        );
+    }
+
+    #[test]
+    fn template() {
+        let options = DisplayOptions::default();
+        let line = display_template(
+            "{port} {ts} {status} ch={ch} {d1}/{d2} {note}",
+            2,
+            1_500_000,
+            &[0x91, 60, 100],
+            &options,
+        );
+        assert_eq!(line, "2 1.500000 Note On ch=2 60/100 C3");
+
+        let line = display_template("{{{status}}} {ch}{note} {raw}", 0, 0, &[0xF8], &options);
+        assert_eq!(line, "{Timing Clock}  248");
+
+        let line = display_template("{foo} {d1", 0, 0, &[0xC0, 5], &options);
+        assert_eq!(line, "{foo} {d1");
+    }
+
+    #[test]
+    fn timestamp_formats() {
+        assert_eq!(
+            format_timestamp(1_500_000, TimestampFormat::Raw),
+            "  1.500000"
+        );
+        assert_eq!(format_timestamp(250, TimestampFormat::Delta), "  0.000250");
+        assert_eq!(
+            format_timestamp(80_000_123_456, TimestampFormat::Wallclock),
+            "22:13:20.123"
+        );
+        assert_eq!(format_timestamp(0, TimestampFormat::None), "");
+
+        let options = DisplayOptions {
+            timestamp_format: TimestampFormat::None,
+            ..Default::default()
+        };
+        let line = display_default(1, 0, &[0xF8], &options);
+        assert!(line.starts_with("  (1)  Timing Clock"), "{}", line);
+    }
+
+    #[test]
+    fn display_json_short_messages() {
+        for status in [0x80, 0x90, 0xB0, 0xC0, 0xE0, 0xF2] {
+            let line = display_json(0, 0, &[status], &DisplayOptions::default());
+            assert!(line.contains("\"malformed\":true"), "{}", line);
+        }
+    }
+}
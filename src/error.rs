@@ -0,0 +1,116 @@
+//! Error type for monitoring, replaying and decoding messages
+
+/// Errors that can occur when working with ports and files
+#[derive(Debug)]
+pub enum MidimonError {
+    /// No port matching the selection
+    PortNotFound(String),
+    /// The MIDI backend failed to open or use a port
+    Connection(String),
+    /// Reading or writing a file or terminal failed
+    Io(std::io::Error),
+    /// An argument is invalid or not supported in this combination
+    InvalidArgument(String),
+}
+
+impl MidimonError {
+    /// Return the process exit code for the error
+    /// Invalid arguments use the same code as command line usage errors
+    #[must_use]
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            MidimonError::InvalidArgument(_) => 2,
+            MidimonError::PortNotFound(_) => 3,
+            MidimonError::Connection(_) => 4,
+            MidimonError::Io(_) => 5,
+        }
+    }
+}
+
+impl std::fmt::Display for MidimonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MidimonError::PortNotFound(message) => write!(f, "{}", message),
+            MidimonError::Connection(message) => write!(f, "Connection failed: {}", message),
+            MidimonError::Io(err) => write!(f, "I/O error: {}", err),
+            MidimonError::InvalidArgument(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for MidimonError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MidimonError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for MidimonError {
+    fn from(err: std::io::Error) -> Self {
+        MidimonError::Io(err)
+    }
+}
+
+#[cfg(feature = "monitor")]
+impl From<midir::InitError> for MidimonError {
+    fn from(err: midir::InitError) -> Self {
+        MidimonError::Connection(err.to_string())
+    }
+}
+
+#[cfg(feature = "monitor")]
+impl From<midir::PortInfoError> for MidimonError {
+    fn from(err: midir::PortInfoError) -> Self {
+        MidimonError::PortNotFound(err.to_string())
+    }
+}
+
+#[cfg(feature = "monitor")]
+impl<T> From<midir::ConnectError<T>> for MidimonError {
+    fn from(err: midir::ConnectError<T>) -> Self {
+        MidimonError::Connection(err.to_string())
+    }
+}
+
+#[cfg(feature = "monitor")]
+impl From<midir::SendError> for MidimonError {
+    fn from(err: midir::SendError) -> Self {
+        MidimonError::Connection(err.to_string())
+    }
+}
+
+#[cfg(feature = "cli")]
+impl From<ctrlc::Error> for MidimonError {
+    fn from(err: ctrlc::Error) -> Self {
+        MidimonError::Io(std::io::Error::other(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes() {
+        let errors = [
+            MidimonError::PortNotFound(String::from("No input port matching \"foo\".")),
+            MidimonError::Connection(String::from("port not available")),
+            MidimonError::Io(std::io::Error::from(std::io::ErrorKind::NotFound)),
+            MidimonError::InvalidArgument(String::from("Speed factor must be greater than 0.")),
+        ];
+
+        let mut codes: Vec<i32> = errors.iter().map(MidimonError::exit_code).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), errors.len());
+        assert!(codes.iter().all(|&code| code > 1));
+
+        assert_eq!(errors[0].to_string(), "No input port matching \"foo\".");
+        assert_eq!(
+            errors[1].to_string(),
+            "Connection failed: port not available"
+        );
+    }
+}
@@ -0,0 +1,476 @@
+//! Ignoring and filtering of received messages
+//!
+//! [`classify`] decides if a message is shown, ignored by its type or removed by a filter. It has
+//! no side effects, so the display and forwarding of messages is left to the caller.
+
+use crate::messages::{MidiMessage, Status};
+use crate::parse::HexPattern;
+
+/// Ignore flags for certain message types
+#[derive(Copy, Clone)]
+pub struct MessageIgnore {
+    pub note: bool,
+    pub poly_pressure: bool,
+    pub control_change: bool,
+    pub program_change: bool,
+    pub channel_pressure: bool,
+    pub pitch_bend: bool,
+    pub sysex: bool,
+    pub eox: bool,
+    pub mtc_frame: bool,
+    pub song_pos_pointer: bool,
+    pub song_select: bool,
+    pub tune_request: bool,
+    pub clock: bool,
+    pub start: bool,
+    pub continue_: bool,
+    pub stop: bool,
+    pub sensing: bool,
+    pub reset: bool,
+}
+
+impl MessageIgnore {
+    /// Return ignore flags with all message types set to the given value
+    pub fn new(ignore: bool) -> Self {
+        Self {
+            note: ignore,
+            poly_pressure: ignore,
+            control_change: ignore,
+            program_change: ignore,
+            channel_pressure: ignore,
+            pitch_bend: ignore,
+            sysex: ignore,
+            eox: ignore,
+            mtc_frame: ignore,
+            song_pos_pointer: ignore,
+            song_select: ignore,
+            tune_request: ignore,
+            clock: ignore,
+            start: ignore,
+            continue_: ignore,
+            stop: ignore,
+            sensing: ignore,
+            reset: ignore,
+        }
+    }
+
+    /// Set ignore flags for all message types covered by a type argument
+    pub fn set(&mut self, message_type: &str, ignore: bool) {
+        let statuses: &[Status] = match message_type {
+            "note" => &[Status::NoteOff, Status::NoteOn],
+            "realtime" => &[
+                Status::TimingClock,
+                Status::Start,
+                Status::Continue,
+                Status::Stop,
+                Status::ActiveSensing,
+                Status::SystemReset,
+            ],
+            "transport" => &[Status::Start, Status::Continue, Status::Stop],
+            "system" => &[
+                Status::SystemExclusive,
+                Status::EndOfExclusive,
+                Status::MtcQuarterFrame,
+                Status::SongPositionPointer,
+                Status::SongSelect,
+                Status::TuneRequest,
+                Status::TimingClock,
+                Status::Start,
+                Status::Continue,
+                Status::Stop,
+                Status::ActiveSensing,
+                Status::SystemReset,
+            ],
+            _ => match message_type.parse::<Status>() {
+                Ok(status) => &[status][..],
+                Err(_) => &[],
+            },
+        };
+
+        for status in statuses {
+            self.set_status(*status, ignore);
+        }
+    }
+
+    /// Set flag for the message type of a single status
+    pub fn set_status(&mut self, status: Status, ignore: bool) {
+        match status {
+            Status::NoteOff | Status::NoteOn => self.note = ignore,
+            Status::PolyKeyPressure => self.poly_pressure = ignore,
+            Status::ControlChange => self.control_change = ignore,
+            Status::ProgramChange => self.program_change = ignore,
+            Status::ChannelPressure => self.channel_pressure = ignore,
+            Status::PitchBend => self.pitch_bend = ignore,
+            Status::SystemExclusive => self.sysex = ignore,
+            Status::MtcQuarterFrame => self.mtc_frame = ignore,
+            Status::SongPositionPointer => self.song_pos_pointer = ignore,
+            Status::SongSelect => self.song_select = ignore,
+            Status::TuneRequest => self.tune_request = ignore,
+            Status::TimingClock => self.clock = ignore,
+            Status::Start => self.start = ignore,
+            Status::Continue => self.continue_ = ignore,
+            Status::Stop => self.stop = ignore,
+            Status::ActiveSensing => self.sensing = ignore,
+            Status::SystemReset => self.reset = ignore,
+            Status::EndOfExclusive => self.eox = ignore,
+            Status::Error => (),
+        }
+    }
+
+    /// Return if the flag for the message type of a status byte is set
+    pub fn matches(&self, status: u8) -> bool {
+        match Status::try_from(status) {
+            Ok(Status::NoteOff | Status::NoteOn) => self.note,
+            Ok(Status::PolyKeyPressure) => self.poly_pressure,
+            Ok(Status::ControlChange) => self.control_change,
+            Ok(Status::ProgramChange) => self.program_change,
+            Ok(Status::ChannelPressure) => self.channel_pressure,
+            Ok(Status::PitchBend) => self.pitch_bend,
+            Ok(Status::SystemExclusive) => self.sysex,
+            Ok(Status::EndOfExclusive) => self.eox,
+            Ok(Status::MtcQuarterFrame) => self.mtc_frame,
+            Ok(Status::SongPositionPointer) => self.song_pos_pointer,
+            Ok(Status::SongSelect) => self.song_select,
+            Ok(Status::TuneRequest) => self.tune_request,
+            Ok(Status::TimingClock) => self.clock,
+            Ok(Status::Start) => self.start,
+            Ok(Status::Continue) => self.continue_,
+            Ok(Status::Stop) => self.stop,
+            Ok(Status::ActiveSensing) => self.sensing,
+            Ok(Status::SystemReset) => self.reset,
+            _ => false,
+        }
+    }
+}
+
+/// Filter to show only certain message types
+#[derive(Clone, Default)]
+pub struct MessageFilter {
+    /// Bitmask of channels 0-15
+    pub channels: Option<u16>,
+    pub note_range: Option<(u8, u8)>,
+    /// Bitmask of controller numbers
+    pub controllers: Option<u128>,
+    pub velocity_range: Option<(u8, u8)>,
+    /// Apply velocity range to note off messages as well
+    pub velocity_note_off: bool,
+    /// Show only unknown or malformed messages
+    pub errors_only: bool,
+    /// Show only messages matching any of the patterns if not empty
+    pub hex_patterns: Vec<HexPattern>,
+}
+
+/// Result of classifying a message by the ignore flags and the filter
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    /// The message is shown
+    Show,
+    /// The message type is ignored
+    Ignore,
+    /// The message is removed by the filter
+    FilteredOut,
+}
+
+/// Return if a complete message is shown, ignored or filtered out
+/// Empty messages are filtered out.
+pub fn classify(message: &[u8], ignore: &MessageIgnore, filter: &MessageFilter) -> Action {
+    let Some(&status_byte) = message.first() else {
+        return Action::FilteredOut;
+    };
+    let status = if status_byte >= 0xF0 {
+        status_byte
+    } else {
+        status_byte & 0xF0
+    };
+
+    if filter.errors_only && MidiMessage::from_array(message).is_valid() {
+        return Action::FilteredOut;
+    }
+
+    if ignore.matches(status) {
+        return Action::Ignore;
+    }
+
+    // Only channel voice messages 0x80-0xEF have a channel, system messages always pass
+    if let Some(channels) = filter.channels {
+        if (0x80..0xF0).contains(&status_byte) && (channels & (1 << (status_byte & 0x0F)) == 0) {
+            return Action::FilteredOut;
+        }
+    }
+
+    if let Some((low, high)) = filter.note_range {
+        if (status == Status::NoteOff as u8
+            || status == Status::NoteOn as u8
+            || status == Status::PolyKeyPressure as u8)
+            && message.len() > 1
+            && (message[1] < low || message[1] > high)
+        {
+            return Action::FilteredOut;
+        }
+    }
+
+    if let Some((min, max)) = filter.velocity_range {
+        let note_off = status == Status::NoteOff as u8
+            || MidiMessage::from_array(message).is_implicit_note_off();
+        if (status == Status::NoteOn as u8 || status == Status::NoteOff as u8)
+            && (!note_off || filter.velocity_note_off)
+            && message.len() > 2
+            && (message[2] < min || message[2] > max)
+        {
+            return Action::FilteredOut;
+        }
+    }
+
+    if let Some(controllers) = filter.controllers {
+        if status == Status::ControlChange as u8
+            && message.len() > 1
+            && (message[1] > 127 || controllers & (1 << message[1]) == 0)
+        {
+            return Action::FilteredOut;
+        }
+    }
+
+    if !filter.hex_patterns.is_empty()
+        && !filter
+            .hex_patterns
+            .iter()
+            .any(|pattern| pattern.matches(message))
+    {
+        return Action::FilteredOut;
+    }
+
+    Action::Show
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One message of each status with typical data bytes
+    const MESSAGES: [&[u8]; 18] = [
+        &[0x80, 60, 64],
+        &[0x90, 60, 100],
+        &[0xA0, 60, 10],
+        &[0xB0, 7, 100],
+        &[0xC0, 5],
+        &[0xD0, 20],
+        &[0xE0, 0, 64],
+        &[0xF0, 0x7E, 0x7F, 0x06, 0x01, 0xF7],
+        &[0xF1, 0x10],
+        &[0xF2, 0, 0],
+        &[0xF3, 1],
+        &[0xF6],
+        &[0xF7],
+        &[0xF8],
+        &[0xFA],
+        &[0xFB],
+        &[0xFC],
+        &[0xFE],
+    ];
+
+    #[test]
+    fn show_all_without_ignore_and_filter() {
+        let ignore = MessageIgnore::new(false);
+        let filter = MessageFilter::default();
+
+        for message in MESSAGES.iter().copied().chain([&[0xFF][..]]) {
+            assert_eq!(
+                classify(message, &ignore, &filter),
+                Action::Show,
+                "{:?}",
+                message
+            );
+        }
+        assert_eq!(classify(&[], &ignore, &filter), Action::FilteredOut);
+    }
+
+    #[test]
+    fn ignore_each_type() {
+        let filter = MessageFilter::default();
+
+        for (message_type, statuses) in [
+            ("note", &[0x80, 0x90][..]),
+            ("polyat", &[0xA0]),
+            ("cc", &[0xB0]),
+            ("pc", &[0xC0]),
+            ("at", &[0xD0]),
+            ("pb", &[0xE0]),
+            ("sysex", &[0xF0]),
+            ("mtc", &[0xF1]),
+            ("spp", &[0xF2]),
+            ("songsel", &[0xF3]),
+            ("tune", &[0xF6]),
+            ("eox", &[0xF7]),
+            ("clock", &[0xF8]),
+            ("start", &[0xFA]),
+            ("continue", &[0xFB]),
+            ("stop", &[0xFC]),
+            ("sensing", &[0xFE]),
+            ("reset", &[0xFF]),
+            ("transport", &[0xFA, 0xFB, 0xFC]),
+        ] {
+            let mut ignore = MessageIgnore::new(false);
+            ignore.set(message_type, true);
+
+            for message in MESSAGES
+                .iter()
+                .copied()
+                .chain([&[0xFF][..], &[0x9F, 60, 100]])
+            {
+                // Channel messages are ignored on all channels
+                let status = if message[0] >= 0xF0 {
+                    message[0]
+                } else {
+                    message[0] & 0xF0
+                };
+                let expected = if statuses.contains(&status) {
+                    Action::Ignore
+                } else {
+                    Action::Show
+                };
+                assert_eq!(
+                    classify(message, &ignore, &filter),
+                    expected,
+                    "{} {:?}",
+                    message_type,
+                    message
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn channel_filter() {
+        let ignore = MessageIgnore::new(false);
+        let filter = MessageFilter {
+            // Channels are 0-based in the mask, so bit 0 is channel 1 and bit 9 is channel 10
+            channels: Some(1 << 0 | 1 << 9),
+            ..Default::default()
+        };
+
+        for (message, expected) in [
+            (&[0x90, 60, 100][..], Action::Show),
+            (&[0x91, 60, 100], Action::FilteredOut),
+            (&[0x99, 36, 100], Action::Show),
+            (&[0x9A, 36, 100], Action::FilteredOut),
+            (&[0xB8, 7, 100], Action::FilteredOut),
+            (&[0xEF, 0, 64], Action::FilteredOut),
+            (&[0xF8], Action::Show),
+            (&[0xF2, 0, 0], Action::Show),
+        ] {
+            assert_eq!(
+                classify(message, &ignore, &filter),
+                expected,
+                "{:?}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn channel_filter_passes_system_messages() {
+        let ignore = MessageIgnore::new(false);
+
+        for channel in 0..16 {
+            let filter = MessageFilter {
+                channels: Some(1 << channel),
+                ..Default::default()
+            };
+            for message in MESSAGES
+                .iter()
+                .copied()
+                .filter(|message| message[0] >= 0xF0)
+            {
+                assert_eq!(
+                    classify(message, &ignore, &filter),
+                    Action::Show,
+                    "channel {} {:?}",
+                    channel,
+                    message
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn note_and_velocity_filter() {
+        let ignore = MessageIgnore::new(false);
+        let filter = MessageFilter {
+            note_range: Some((36, 48)),
+            velocity_range: Some((10, 127)),
+            ..Default::default()
+        };
+
+        for (message, expected) in [
+            (&[0x90, 36, 100][..], Action::Show),
+            (&[0x90, 49, 100], Action::FilteredOut),
+            (&[0xA0, 60, 100], Action::FilteredOut),
+            (&[0x90, 40, 5], Action::FilteredOut),
+            // Note off sent as note on with velocity 0 by running status devices
+            (&[0x90, 40, 0], Action::Show),
+            (&[0x80, 40, 0], Action::Show),
+            (&[0xB0, 1, 0], Action::Show),
+        ] {
+            assert_eq!(
+                classify(message, &ignore, &filter),
+                expected,
+                "{:?}",
+                message
+            );
+        }
+
+        let filter = MessageFilter {
+            velocity_note_off: true,
+            ..filter
+        };
+        assert_eq!(
+            classify(&[0x90, 40, 0], &ignore, &filter),
+            Action::FilteredOut
+        );
+        assert_eq!(
+            classify(&[0x80, 40, 0], &ignore, &filter),
+            Action::FilteredOut
+        );
+    }
+
+    #[test]
+    fn controller_and_pattern_filter() {
+        let ignore = MessageIgnore::new(false);
+        let filter = MessageFilter {
+            controllers: Some(1 << 7),
+            hex_patterns: vec!["B0 ?? ??".parse().unwrap(), "F8".parse().unwrap()],
+            ..Default::default()
+        };
+
+        for (message, expected) in [
+            (&[0xB0, 7, 100][..], Action::Show),
+            (&[0xB0, 1, 100], Action::FilteredOut),
+            (&[0xB1, 7, 100], Action::FilteredOut),
+            (&[0xF8], Action::Show),
+            (&[0xFE], Action::FilteredOut),
+        ] {
+            assert_eq!(
+                classify(message, &ignore, &filter),
+                expected,
+                "{:?}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn errors_only() {
+        let ignore = MessageIgnore::new(false);
+        let filter = MessageFilter {
+            errors_only: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            classify(&[0x90, 60, 100], &ignore, &filter),
+            Action::FilteredOut
+        );
+        assert_eq!(classify(&[0x90, 60], &ignore, &filter), Action::Show);
+        assert_eq!(classify(&[0x3C, 0x40], &ignore, &filter), Action::Show);
+    }
+}
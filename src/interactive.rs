@@ -0,0 +1,93 @@
+//! Keyboard control of a running monitor session
+
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use midimon::display::DisplayOptions;
+use midimon::error::MidimonError;
+use midimon::stats::Stats;
+
+/// Terminal in non-canonical mode without echo, restored when dropped
+pub struct RawTerminal {
+    saved_settings: String,
+}
+
+impl RawTerminal {
+    /// Switch the terminal to reading single key presses
+    pub fn enable() -> Result<Self, MidimonError> {
+        if !cfg!(unix) {
+            return Err(MidimonError::InvalidArgument(String::from(
+                "Interactive mode is not supported on this platform",
+            )));
+        }
+
+        let saved_settings = stty(&["-g"])?;
+        stty(&["-icanon", "-echo", "min", "1"])?;
+
+        Ok(Self {
+            saved_settings: saved_settings.trim().to_string(),
+        })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        stty(&[&self.saved_settings]).ok();
+    }
+}
+
+/// Run stty on the terminal connected to stdin and return its output
+fn stty(args: &[&str]) -> Result<String, MidimonError> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(MidimonError::InvalidArgument(String::from(
+            "Interactive mode requires a terminal",
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Handle key presses on a separate thread until stdin is closed
+/// Space pauses or resumes the display, c clears the terminal and s shows the statistics
+pub fn spawn_key_handler(
+    paused: Arc<AtomicBool>,
+    stats: Arc<Mutex<Stats>>,
+    start: Instant,
+    options: DisplayOptions,
+) {
+    std::thread::spawn(move || {
+        for key in std::io::stdin().lock().bytes() {
+            match key {
+                Ok(b' ') => {
+                    let was_paused = paused.fetch_xor(true, Ordering::SeqCst);
+                    println!("{}", if was_paused { "Resumed" } else { "Paused" });
+                }
+                Ok(b'c') => {
+                    print!("\x1b[2J\x1b[H");
+                    std::io::stdout().flush().ok();
+                }
+                Ok(b's') => {
+                    println!();
+                    println!(
+                        "{}",
+                        stats
+                            .lock()
+                            .unwrap()
+                            .summary(start.elapsed(), false, &options)
+                    );
+                    println!();
+                }
+                Ok(_) => (),
+                Err(_) => break,
+            }
+        }
+    });
+}
@@ -0,0 +1,17 @@
+//! Parsing and formatting of MIDI messages
+//!
+//! This is the library part of midimon. Without the `monitor` feature, it has no dependency
+//! on any MIDI backend and can be used for offline parsing of MIDI data. The `monitor` feature
+//! adds the [`monitor::Monitor`] builder for receiving messages from input ports via midir.
+
+pub mod decode;
+pub mod display;
+pub mod error;
+pub mod filter;
+pub mod messages;
+#[cfg(feature = "monitor")]
+pub mod monitor;
+pub mod parse;
+pub mod smf;
+pub mod stats;
+pub mod sysex;
@@ -1,61 +1,272 @@
 extern crate clap;
+extern crate ctrlc;
 extern crate midir;
 
-mod messages;
+mod config;
+mod dashboard;
+mod interactive;
+mod offline;
+mod replay;
+mod send;
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, IsTerminal, Stdout, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use clap::{
+    builder::PossibleValue, error::ErrorKind, parser::ValueSource, value_parser, Arg, ArgAction,
+    ArgMatches, Command,
+};
+use midimon::decode::{
+    BankState, Cc14State, ClockState, HeldNotes, MtcSequence, MtcState, NoteDuration,
+    NoteDurations, NoteWatch, ParameterEvent, ParameterState,
+};
+use midimon::display::{
+    csv_header, display_cc14, display_csv, display_default, display_hexdump, display_json,
+    display_keyboard, display_malformed, display_min, display_min_hex, display_parameter,
+    display_raw, display_template, format_timestamp, keyboard_octave, min_annotation,
+    truncate_byte_listing, DisplayFormat, DisplayOptions, HexFormat, TimestampFormat,
+};
+use midimon::error::MidimonError;
+use midimon::filter::{classify, Action, MessageFilter, MessageIgnore};
+use midimon::messages::{MiddleC, MidiMessage, NoteNaming, NoteStyle, Status, TimeSignature};
+use midimon::monitor::select_ports;
+use midimon::parse::{parse_hex_bytes, HexPattern};
+use midimon::smf::SmfRecorder;
+use midimon::stats::{is_realtime, CcTracker, ClockJitter, Stats, VelocityHistogram};
+use midir::{
+    ConnectError, MidiIO, MidiInput, MidiInputConnection, MidiOutput, MidiOutputConnection,
+};
+
+/// Writer for display lines on the terminal and in the output file if enabled
+/// Lines are buffered and flushed periodically unless line buffering is enabled
+struct DisplayWriter {
+    stdout: BufWriter<Stdout>,
+    file: Option<BufWriter<File>>,
+    /// Directory for the per-port output files, which are created with the first line of a port
+    split_dir: Option<PathBuf>,
+    port_files: BTreeMap<usize, BufWriter<File>>,
+    /// Header written at the start of each output file
+    header: Option<&'static str>,
+    line_buffered: bool,
+    /// Maximum width of lines on the terminal, longer byte listings are truncated
+    max_width: Option<usize>,
+    /// Separator of the bytes in the listings to truncate
+    separator: String,
+}
 
-use clap::{builder::PossibleValue, value_parser, Arg, ArgAction, Command};
-use messages::{MidiMessage, Status};
-use midir::{ConnectError, MidiInput, MidiInputConnection};
+impl DisplayWriter {
+    /// Write a line of a port to the terminal and the output files
+    /// Only the terminal line is truncated, the output files get the full line
+    fn write_line(&mut self, port_id: usize, line: &str) {
+        match self.max_width {
+            Some(max_width) => writeln!(
+                self.stdout,
+                "{}",
+                truncate_byte_listing(line, max_width, &self.separator)
+            ),
+            None => writeln!(self.stdout, "{}", line),
+        }
+        .ok();
 
-/// Display format options
-#[derive(Copy, Clone)]
-enum DisplayFormat {
-    Default,
-    Raw,
-    Min,
-    MinHex,
+        if let Some(file) = &mut self.file {
+            writeln!(file, "{}", line)
+                .unwrap_or_else(|err| eprintln!("Error writing output: {}", err));
+        }
+
+        if let Some(file) = self.port_file(port_id) {
+            writeln!(file, "{}", line)
+                .unwrap_or_else(|err| eprintln!("Error writing output: {}", err));
+        }
+
+        if self.line_buffered {
+            self.flush();
+        }
+    }
+
+    /// Write the header to the terminal if enabled and the output file, and remember it for the
+    /// per-port files
+    fn write_header(&mut self, header: &'static str, terminal: bool) -> std::io::Result<()> {
+        self.header = Some(header);
+
+        if terminal {
+            writeln!(self.stdout, "{}", header)?;
+            self.stdout.flush()?;
+        }
+
+        match &mut self.file {
+            Some(file) => writeln!(file, "{}", header),
+            None => Ok(()),
+        }
+    }
+
+    /// Return the output file of a port, creating it if necessary
+    /// Returns `None` if the output is not split or the file can't be created
+    fn port_file(&mut self, port_id: usize) -> Option<&mut BufWriter<File>> {
+        let dir = self.split_dir.as_ref()?;
+
+        if !self.port_files.contains_key(&port_id) {
+            let path = dir.join(format!("port-{}.log", port_id));
+            let mut file = match File::create(&path) {
+                Ok(file) => BufWriter::new(file),
+                Err(err) => {
+                    eprintln!("Error creating {}: {}", path.display(), err);
+                    return None;
+                }
+            };
+            if let Some(header) = self.header {
+                writeln!(file, "{}", header)
+                    .unwrap_or_else(|err| eprintln!("Error writing output: {}", err));
+            }
+            self.port_files.insert(port_id, file);
+        }
+
+        self.port_files.get_mut(&port_id)
+    }
+
+    /// Write all buffered lines
+    fn flush(&mut self) {
+        self.stdout.flush().ok();
+
+        for file in self.file.iter_mut().chain(self.port_files.values_mut()) {
+            file.flush()
+                .unwrap_or_else(|err| eprintln!("Error writing output: {}", err));
+        }
+    }
 }
 
-/// Ignore flags for certain message types
+/// Shared writer for displayed messages
+type OutputWriter = Arc<Mutex<DisplayWriter>>;
+
+/// Width of port names in display lines
+const PORT_NAME_WIDTH: usize = 20;
+
+/// Interval for flushing buffered display lines if not given as argument
+const FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Shared connection for forwarding received messages
+type ThruConnection = Arc<Mutex<MidiOutputConnection>>;
+
+/// Time of the last message per port for ports sending active sensing
+type SensingTimes = Arc<Mutex<BTreeMap<usize, Instant>>>;
+
+/// Time without messages after which a port sending active sensing is considered disconnected
+const SENSING_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// Result of connecting to an input port
+type Connection = Result<MidiInputConnection<ReceiveArgs>, ConnectError<MidiInput>>;
+
+/// Interval for checking if disconnected ports are available again
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Messages counted for sequence numbers
 #[derive(Copy, Clone)]
-struct MessageIgnore {
-    note: bool,
-    poly_pressure: bool,
-    control_change: bool,
-    program_change: bool,
-    channel_pressure: bool,
-    pitch_bend: bool,
-    sysex: bool,
-    mtc_frame: bool,
-    song_pos_pointer: bool,
-    song_select: bool,
-    tune_request: bool,
-    clock: bool,
-    start: bool,
-    continue_: bool,
-    stop: bool,
-    sensing: bool,
-    reset: bool,
-}
-
-/// Filter to show only certain message types
+enum Numbering {
+    /// Only messages passing the ignore options and filters
+    Shown,
+    /// All received messages
+    All,
+}
+
+/// Held notes of all ports and the lowest octave shown by the keyboard
+struct KeyboardState {
+    held: HeldNotes,
+    octave: u8,
+}
+
+/// Octave shown first by the keyboard, starting at note 60
+const KEYBOARD_START_OCTAVE: u8 = 5;
+
+/// Time after which a pending line of repeated messages is shown
+const DEDUP_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Display line of the last message, held back until the message is no longer repeated
+struct PendingRepeat {
+    port_id: usize,
+    message: Vec<u8>,
+    line: String,
+    count: usize,
+    last_time: Instant,
+}
+
+impl PendingRepeat {
+    /// Return the display line with the number of repeats if there are any
+    fn line(&self) -> String {
+        if self.count > 1 {
+            format!("{}  (x{})", self.line, self.count)
+        } else {
+            self.line.clone()
+        }
+    }
+}
+
+/// Pending line shared by all ports for collapsing repeated messages
+type RepeatState = Arc<Mutex<Option<PendingRepeat>>>;
+
+/// Connection to a hardware input port, identified by its name for reconnecting
+struct PortConnection {
+    port_id: usize,
+    name: String,
+    connection: Option<MidiInputConnection<ReceiveArgs>>,
+}
+
+/// Display rate limit for certain message types
 #[derive(Copy, Clone)]
-struct MessageFilter {
-    channel: Option<u8>,
+struct Throttle {
+    types: MessageIgnore,
+    /// Minimum time between displayed messages in microseconds
+    interval: u64,
+    last_timestamp: Option<u64>,
 }
 
+/// Minimum time between two terminal bells
+const BEEP_INTERVAL: Duration = Duration::from_millis(250);
+
 /// Application main function
 fn main() {
     let command = Command::new(env!("CARGO_PKG_NAME"))
         .version(env!("CARGO_PKG_VERSION"))
         .author(env!("CARGO_PKG_AUTHORS"))
+        .args_override_self(true)
         .arg(
             Arg::new("port")
                 .short('p')
                 .long("port")
-                .value_name("ID")
-                .help("Monitor single port")
-                .value_parser(value_parser!(u8)),
+                .value_name("ID|NAME")
+                .num_args(1..)
+                .value_delimiter(',')
+                .help("Monitor ports by id or ports matching name"),
+        )
+        .arg(
+            Arg::new("port-names")
+                .long("port-names")
+                .action(ArgAction::SetTrue)
+                .help("Show port names after the port ids in display lines"),
+        )
+        .arg(
+            Arg::new("virtual")
+                .long("virtual")
+                .value_name("NAME")
+                .conflicts_with("port")
+                .help("Create a virtual input port for other applications instead (Linux and macOS only)"),
+        )
+        .arg(
+            Arg::new("thru")
+                .long("thru")
+                .value_name("ID|NAME")
+                .help("Forward displayed messages to an output port"),
+        )
+        .arg(
+            Arg::new("thru-all")
+                .long("thru-all")
+                .action(ArgAction::SetTrue)
+                .requires("thru")
+                .help("Forward all received messages, ignoring filters"),
         )
         .arg(
             Arg::new("ignore")
@@ -64,28 +275,95 @@ fn main() {
                 .value_name("TYPE")
                 .num_args(1..)
                 .help("Ignore certain message types")
-                .value_parser([
-                    PossibleValue::new("note"),
-                    PossibleValue::new("polyat"),
-                    PossibleValue::new("cc"),
-                    PossibleValue::new("pc"),
-                    PossibleValue::new("at"),
-                    PossibleValue::new("pb"),
-                    PossibleValue::new("sysex"),
-                    PossibleValue::new("clock"),
-                    PossibleValue::new("sensing"),
-                    PossibleValue::new("realtime"),
-                    PossibleValue::new("transport"),
-                    PossibleValue::new("system"),
-                ]),
+                .value_parser(message_types()),
+        )
+        .arg(
+            Arg::new("only")
+                .long("only")
+                .value_name("TYPE")
+                .num_args(1..)
+                .conflicts_with("ignore")
+                .help("Show only certain message types")
+                .value_parser(message_types()),
+        )
+        .arg(
+            Arg::new("throttle")
+                .long("throttle")
+                .value_name("TYPE:HZ")
+                .action(ArgAction::Append)
+                .help("Show messages of a type at most HZ times per second, e.g. clock:2")
+                .value_parser(parse_throttle),
+        )
+        .arg(
+            Arg::new("beep-on")
+                .long("beep-on")
+                .value_name("TYPE")
+                .num_args(1..)
+                .help("Ring the terminal bell when messages of certain types arrive")
+                .value_parser(message_types()),
         )
         .arg(
             Arg::new("channel")
                 .short('c')
                 .long("channel")
-                .value_name("CHANNEL")
-                .help("Show only messages from specified channel")
-                .value_parser(value_parser!(u8)),
+                .value_name("LIST")
+                .help("Show only messages from channels in list, e.g. 10 or 1,3,5-8")
+                .value_parser(|value: &str| parse_number_set(value, 0, 16)),
+        )
+        .arg(
+            Arg::new("exclude-channel")
+                .long("exclude-channel")
+                .value_name("LIST")
+                .help("Hide messages from channels in list, takes precedence over --channel")
+                .value_parser(|value: &str| parse_number_set(value, 0, 16)),
+        )
+        .arg(
+            Arg::new("filter-note")
+                .long("filter-note")
+                .value_name("LOW-HIGH")
+                .help("Show only note messages within range, e.g. 36-96, 60- or -72")
+                .value_parser(parse_note_range),
+        )
+        .arg(
+            Arg::new("min-velocity")
+                .long("min-velocity")
+                .value_name("N")
+                .help("Show only note on messages with velocity N or above")
+                .value_parser(value_parser!(u8).range(0..=127)),
+        )
+        .arg(
+            Arg::new("max-velocity")
+                .long("max-velocity")
+                .value_name("N")
+                .help("Show only note on messages with velocity N or below")
+                .value_parser(value_parser!(u8).range(0..=127)),
+        )
+        .arg(
+            Arg::new("velocity-note-off")
+                .long("velocity-note-off")
+                .action(ArgAction::SetTrue)
+                .help("Apply velocity filter to note off messages as well"),
+        )
+        .arg(
+            Arg::new("errors-only")
+                .long("errors-only")
+                .action(ArgAction::SetTrue)
+                .help("Show only unknown or malformed messages"),
+        )
+        .arg(
+            Arg::new("match-hex")
+                .long("match-hex")
+                .value_name("PATTERN")
+                .action(ArgAction::Append)
+                .help("Show only messages matching hex bytes with ?? as wildcard, e.g. \"B0 07 ??\"")
+                .value_parser(|value: &str| value.parse::<HexPattern>()),
+        )
+        .arg(
+            Arg::new("filter-cc")
+                .long("filter-cc")
+                .value_name("LIST")
+                .help("Show only control changes with controller numbers in list, e.g. 1,7,20-30")
+                .value_parser(|value: &str| parse_number_set(value, 0, 127)),
         )
         .arg(
             Arg::new("format")
@@ -99,8 +377,477 @@ fn main() {
                     PossibleValue::new("raw"),
                     PossibleValue::new("min"),
                     PossibleValue::new("min-hex"),
+                    PossibleValue::new("json"),
+                    PossibleValue::new("csv"),
+                    PossibleValue::new("hexdump"),
+                ]),
+        )
+        .arg(
+            Arg::new("annotate")
+                .long("annotate")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("template")
+                .help("Append the message type as comment in min and min-hex format"),
+        )
+        .arg(
+            Arg::new("hex-prefix")
+                .long("hex-prefix")
+                .value_name("PREFIX")
+                .help("Text in front of each byte in min-hex format [default: 0x]"),
+        )
+        .arg(
+            Arg::new("hex-separator")
+                .long("hex-separator")
+                .value_name("SEPARATOR")
+                .help("Text between the bytes in min-hex format [default: \", \"]"),
+        )
+        .arg(
+            Arg::new("uppercase")
+                .long("uppercase")
+                .action(ArgAction::SetTrue)
+                .overrides_with("lowercase")
+                .help("Show hex digits in uppercase in min-hex format (default)"),
+        )
+        .arg(
+            Arg::new("lowercase")
+                .long("lowercase")
+                .action(ArgAction::SetTrue)
+                .overrides_with("uppercase")
+                .help("Show hex digits in lowercase in min-hex format"),
+        )
+        .arg(
+            Arg::new("max-line-width")
+                .long("max-line-width")
+                .value_name("N")
+                .conflicts_with("template")
+                .help(
+                    "Truncate byte listings in raw, min and min-hex format \
+                     to N characters on the terminal",
+                )
+                .value_parser(value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("template")
+                .long("template")
+                .value_name("TEMPLATE")
+                .conflicts_with("format")
+                .help(
+                    "Display messages using a template with placeholders \
+                     {port}, {ts}, {status}, {ch}, {d1}, {d2}, {note} and {raw}",
+                ),
+        )
+        .arg(
+            Arg::new("align")
+                .long("align")
+                .action(ArgAction::SetTrue)
+                .help("Show all message types in the same columns in default format"),
+        )
+        .arg(
+            Arg::new("bars")
+                .long("bars")
+                .action(ArgAction::SetTrue)
+                .help("Show pressure, control change and pitch bend values as bars"),
+        )
+        .arg(
+            Arg::new("gm")
+                .long("gm")
+                .action(ArgAction::SetTrue)
+                .help("Show General MIDI instrument names for program changes"),
+        )
+        .arg(
+            Arg::new("hex-data")
+                .long("hex-data")
+                .action(ArgAction::SetTrue)
+                .help("Show data bytes in hex in the default format"),
+        )
+        .arg(
+            Arg::new("pretty-sysex")
+                .long("pretty-sysex")
+                .action(ArgAction::SetTrue)
+                .help("Decode Roland and Yamaha parameter SysEx messages"),
+        )
+        .arg(
+            Arg::new("verify-checksum")
+                .long("verify-checksum")
+                .action(ArgAction::SetTrue)
+                .help("Verify the checksums of Roland, Yamaha and sample dump SysEx messages"),
+        )
+        .arg(
+            Arg::new("with-raw")
+                .long("with-raw")
+                .action(ArgAction::SetTrue)
+                .help("Append the raw message bytes in the default format"),
+        )
+        .arg(
+            Arg::new("middle-c")
+                .long("middle-c")
+                .value_name("OCTAVE")
+                .help("Octave of note 60 in note names")
+                .default_value("3")
+                .value_parser([
+                    PossibleValue::new("3").help("Yamaha convention, note 60 is C3"),
+                    PossibleValue::new("4").help("Scientific pitch notation, note 60 is C4"),
+                ]),
+        )
+        .arg(
+            Arg::new("note-style")
+                .long("note-style")
+                .value_name("STYLE")
+                .help("Spelling of note names")
+                .default_value("scientific")
+                .value_parser([
+                    PossibleValue::new("scientific").help("Letters and octave numbers like C3"),
+                    PossibleValue::new("solfege").help("Syllables and octave numbers like Do3"),
+                    PossibleValue::new("helmholtz")
+                        .help("Letter case and primes or commas like c' for note 60"),
+                ]),
+        )
+        .arg(
+            Arg::new("flats")
+                .long("flats")
+                .action(ArgAction::SetTrue)
+                .help("Show note names with flats instead of sharps"),
+        )
+        .arg(
+            Arg::new("numeric-notes")
+                .long("numeric-notes")
+                .action(ArgAction::SetTrue)
+                .help("Show note numbers without note names"),
+        )
+        .arg(
+            Arg::new("drum-map")
+                .long("drum-map")
+                .value_name("CHANNEL")
+                .num_args(0..=1)
+                .default_missing_value("10")
+                .help("Show General MIDI drum names for notes on CHANNEL [default: 10]")
+                .value_parser(value_parser!(u8).range(1..=16)),
+        )
+        .arg(
+            Arg::new("assume-channel")
+                .long("assume-channel")
+                .value_name("CHANNEL")
+                .help("Show all channel messages on CHANNEL instead of their own, without filtering")
+                .value_parser(value_parser!(u8).range(1..=16)),
+        )
+        .arg(
+            Arg::new("zero-based-channels")
+                .long("zero-based-channels")
+                .action(ArgAction::SetTrue)
+                .help("Show and select channels as 0-15 instead of 1-16"),
+        )
+        .arg(
+            Arg::new("group-by-channel")
+                .long("group-by-channel")
+                .action(ArgAction::SetTrue)
+                .help("Indent channel messages by their channel in the default format"),
+        )
+        .arg(
+            Arg::new("msb-first")
+                .long("msb-first")
+                .action(ArgAction::SetTrue)
+                .help("Combine pitch bend and song position data bytes with the MSB first, for devices not following the specification"),
+        )
+        .arg(
+            Arg::new("bend-direction")
+                .long("bend-direction")
+                .action(ArgAction::SetTrue)
+                .help("Show pitch bend direction arrows and mark the center position"),
+        )
+        .arg(
+            Arg::new("follow-song-position")
+                .long("follow-song-position")
+                .action(ArgAction::SetTrue)
+                .help("Show song position pointers in sixteenths and as bar, beat and sixteenth"),
+        )
+        .arg(
+            Arg::new("time-signature")
+                .long("time-signature")
+                .value_name("N/D")
+                .help("Time signature for showing song positions as bars and beats")
+                .default_value("4/4")
+                .value_parser(parse_time_signature),
+        )
+        .arg(
+            Arg::new("bend-semitones")
+                .long("bend-semitones")
+                .value_name("RANGE")
+                .num_args(0..=1)
+                .default_missing_value("2")
+                .help("Show pitch bend in semitones for a bend range of +/- RANGE [default: 2]")
+                .value_parser(value_parser!(f64)),
+        )
+        .arg(
+            Arg::new("freq")
+                .long("freq")
+                .action(ArgAction::SetTrue)
+                .help("Show note frequencies"),
+        )
+        .arg(
+            Arg::new("tuning")
+                .long("tuning")
+                .value_name("A=HZ")
+                .default_value("440")
+                .requires("freq")
+                .help("Reference frequency of A4 for note frequencies")
+                .value_parser(parse_tuning),
+        )
+        .arg(
+            Arg::new("dynamics")
+                .long("dynamics")
+                .action(ArgAction::SetTrue)
+                .help("Show dynamic markings for note on velocities"),
+        )
+        .arg(
+            Arg::new("no-implicit-note-off")
+                .long("no-implicit-note-off")
+                .action(ArgAction::SetTrue)
+                .help("Show note on messages with velocity 0 as note on"),
+        )
+        .arg(
+            Arg::new("no-nrpn")
+                .long("no-nrpn")
+                .action(ArgAction::SetTrue)
+                .help("Show RPN and NRPN sequences as individual control changes"),
+        )
+        .arg(
+            Arg::new("cc14")
+                .long("cc14")
+                .action(ArgAction::SetTrue)
+                .help("Combine control changes 0-31 and 32-63 into 14-bit values"),
+        )
+        .arg(
+            Arg::new("bpm")
+                .long("bpm")
+                .action(ArgAction::SetTrue)
+                .help("Show tempo calculated from timing clock"),
+        )
+        .arg(
+            Arg::new("watch-sensing")
+                .long("watch-sensing")
+                .action(ArgAction::SetTrue)
+                .help("Warn when a device sending active sensing falls silent"),
+        )
+        .arg(
+            Arg::new("reconnect")
+                .long("reconnect")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("virtual")
+                .help("Reconnect to ports by name after they were disconnected"),
+        )
+        .arg(
+            Arg::new("durations")
+                .long("durations")
+                .action(ArgAction::SetTrue)
+                .help("Show how long notes were held on note off"),
+        )
+        .arg(
+            Arg::new("detect-gaps")
+                .long("detect-gaps")
+                .action(ArgAction::SetTrue)
+                .help("Warn about possibly dropped messages in MTC quarter frame sequences"),
+        )
+        .arg(
+            Arg::new("track-cc")
+                .long("track-cc")
+                .action(ArgAction::SetTrue)
+                .help("Show last, minimum and maximum values of all controllers on exit"),
+        )
+        .arg(
+            Arg::new("velocity-histogram")
+                .long("velocity-histogram")
+                .action(ArgAction::SetTrue)
+                .help("Show a histogram of all note on velocities on exit"),
+        )
+        .arg(
+            Arg::new("clock-jitter")
+                .long("clock-jitter")
+                .action(ArgAction::SetTrue)
+                .help("Show statistics of the intervals between timing clock messages on exit"),
+        )
+        .arg(
+            Arg::new("show-channel-activity")
+                .long("show-channel-activity")
+                .action(ArgAction::SetTrue)
+                .help("Show the channels that received any messages on exit"),
+        )
+        .arg(
+            Arg::new("stuck-notes")
+                .long("stuck-notes")
+                .value_name("SECONDS")
+                .help("Warn about notes held longer than the given time")
+                .value_parser(parse_duration),
+        )
+        .arg(
+            Arg::new("poly-limit")
+                .long("poly-limit")
+                .value_name("N")
+                .help("Warn when more than N notes are held at the same time")
+                .value_parser(value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("heartbeat")
+                .long("heartbeat")
+                .value_name("SECONDS")
+                .help("Print a status line to stderr at the given interval while no messages arrive")
+                .value_parser(parse_duration),
+        )
+        .arg(
+            Arg::new("dedup")
+                .long("dedup")
+                .action(ArgAction::SetTrue)
+                .help("Collapse repeated identical messages into a single line"),
+        )
+        .arg(
+            Arg::new("sysex-max")
+                .long("sysex-max")
+                .value_name("BYTES")
+                .help("Maximum size of reassembled SysEx messages")
+                .default_value("1048576")
+                .value_parser(value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("count")
+                .short('n')
+                .long("count")
+                .value_name("N")
+                .help("Exit after N messages")
+                .value_parser(value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("omit-realtime-from-count")
+                .long("omit-realtime-from-count")
+                .action(ArgAction::SetTrue)
+                .help("Leave realtime messages out of the message count, numbering and statistics"),
+        )
+        .arg(
+            Arg::new("duration")
+                .long("duration")
+                .value_name("SECONDS")
+                .help("Exit after monitoring for the given time")
+                .value_parser(parse_duration),
+        )
+        .arg(
+            Arg::new("number")
+                .long("number")
+                .action(ArgAction::SetTrue)
+                .help("Prefix each message with a sequence number"),
+        )
+        .arg(
+            Arg::new("number-all")
+                .long("number-all")
+                .action(ArgAction::SetTrue)
+                .help("Prefix each message with a sequence number counting all received messages"),
+        )
+        .arg(
+            Arg::new("relative")
+                .long("relative")
+                .action(ArgAction::SetTrue)
+                .help("Show timestamps relative to the first message"),
+        )
+        .arg(
+            Arg::new("timestamp-format")
+                .long("timestamp-format")
+                .value_name("FORMAT")
+                .help("Display format of timestamps")
+                .default_value("raw")
+                .value_parser([
+                    PossibleValue::new("raw").help("Seconds as provided by the MIDI driver"),
+                    PossibleValue::new("wallclock").help("Local time of message arrival"),
+                    PossibleValue::new("delta").help("Seconds since the previous message"),
+                    PossibleValue::new("none").help("No timestamps"),
+                ]),
+        )
+        .arg(
+            Arg::new("no-timestamp")
+                .long("no-timestamp")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("timestamp-format")
+                .help("Don't show timestamps, same as --timestamp-format none"),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("FILE")
+                .help("Write messages to file")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("split-output")
+                .long("split-output")
+                .value_name("DIR")
+                .conflicts_with("output")
+                .help("Write the messages of each port to its own file port-N.log in DIR")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("flush-interval")
+                .long("flush-interval")
+                .value_name("MS")
+                .help("Flush buffered output every MS milliseconds [default: 100, line by line on a terminal]")
+                .value_parser(value_parser!(u64).range(1..)),
+        )
+        .arg(
+            Arg::new("line-buffered")
+                .long("line-buffered")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("flush-interval")
+                .help("Flush output after each message"),
+        )
+        .arg(
+            Arg::new("syx-dir")
+                .long("syx-dir")
+                .value_name("DIR")
+                .help("Write each complete SysEx message to a .syx file in DIR")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("smf")
+                .long("smf")
+                .value_name("FILE")
+                .help("Record messages into a Standard MIDI File")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .value_name("WHEN")
+                .help("Colorize output in default format")
+                .default_value("auto")
+                .value_parser([
+                    PossibleValue::new("auto")
+                        .help("Use colors if output is a terminal and NO_COLOR is not set"),
+                    PossibleValue::new("always"),
+                    PossibleValue::new("never"),
                 ]),
         )
+        .arg(
+            Arg::new("stats")
+                .long("stats")
+                .action(ArgAction::SetTrue)
+                .help("Show only statistics on exit instead of messages"),
+        )
+        .arg(
+            Arg::new("interactive")
+                .long("interactive")
+                .action(ArgAction::SetTrue)
+                .help("Enable keys: space to pause/resume, c to clear, s to show statistics"),
+        )
+        .arg(
+            Arg::new("keyboard")
+                .long("keyboard")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["tui", "stats", "template", "output", "split-output"])
+                .help("Show held notes on a keyboard redrawn in place of the message list"),
+        )
+        .arg(
+            Arg::new("tui")
+                .long("tui")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["interactive", "stats", "template", "output", "split-output"])
+                .help("Show a live dashboard instead of the message list"),
+        )
         .arg(
             Arg::new("quiet")
                 .short('q')
@@ -108,12 +855,256 @@ fn main() {
                 .action(ArgAction::SetTrue)
                 .help("Suppress additional output"),
         )
-        .subcommand(Command::new("list").about("List available input ports"));
+        .arg(
+            Arg::new("client-name")
+                .long("client-name")
+                .value_name("NAME")
+                .default_value("midimon")
+                .help("Client name shown to the OS, followed by input or output"),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("List available input or output ports")
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Output ports as JSON array"),
+                )
+                .arg(
+                    Arg::new("outputs")
+                        .long("outputs")
+                        .action(ArgAction::SetTrue)
+                        .help("List output ports instead of input ports"),
+                ),
+        )
+        .subcommand(
+            Command::new("decode")
+                .about("Decode messages from a file or stdin without a MIDI port")
+                .arg(
+                    Arg::new("file")
+                        .value_name("FILE")
+                        .help("File in min, min-hex, json or binary format, stdin if omitted")
+                        .value_parser(value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new("replay")
+                .about("Send messages from a captured file to an output port")
+                .arg(
+                    Arg::new("file")
+                        .value_name("FILE")
+                        .required(true)
+                        .help("Captured file in min, min-hex or json format")
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("port")
+                        .short('p')
+                        .long("port")
+                        .value_name("ID|NAME")
+                        .required(true)
+                        .help("Output port by id or name"),
+                )
+                .arg(
+                    Arg::new("speed")
+                        .short('s')
+                        .long("speed")
+                        .value_name("FACTOR")
+                        .default_value("1.0")
+                        .help("Playback speed factor")
+                        .value_parser(value_parser!(f64)),
+                ),
+        )
+        .subcommand(
+            Command::new("send")
+                .about("Send messages given as arguments to an output port")
+                .arg(
+                    Arg::new("port")
+                        .short('p')
+                        .long("port")
+                        .value_name("ID|NAME")
+                        .required(true)
+                        .help("Output port by id or name"),
+                )
+                .arg(
+                    Arg::new("note-on")
+                        .long("note-on")
+                        .value_names(["CH", "NOTE", "VEL"])
+                        .action(ArgAction::Append)
+                        .help("Send a note on message")
+                        .value_parser(value_parser!(u8).range(0..=127)),
+                )
+                .arg(
+                    Arg::new("note-off")
+                        .long("note-off")
+                        .value_names(["CH", "NOTE", "VEL"])
+                        .action(ArgAction::Append)
+                        .help("Send a note off message")
+                        .value_parser(value_parser!(u8).range(0..=127)),
+                )
+                .arg(
+                    Arg::new("cc")
+                        .long("cc")
+                        .value_names(["CH", "NUM", "VAL"])
+                        .action(ArgAction::Append)
+                        .help("Send a control change message")
+                        .value_parser(value_parser!(u8).range(0..=127)),
+                )
+                .arg(
+                    Arg::new("program")
+                        .long("program")
+                        .value_names(["CH", "NUM"])
+                        .action(ArgAction::Append)
+                        .help("Send a program change message")
+                        .value_parser(value_parser!(u8).range(0..=127)),
+                )
+                .arg(
+                    Arg::new("bytes")
+                        .long("bytes")
+                        .value_name("HEX")
+                        .action(ArgAction::Append)
+                        .help("Send a message given as hex bytes, e.g. 90,3C,40")
+                        .value_parser(|value: &str| {
+                            parse_hex_bytes(value)
+                                .ok_or_else(|| format!("invalid hex bytes \"{}\"", value))
+                        }),
+                )
+                .arg(
+                    Arg::new("interval")
+                        .long("interval")
+                        .value_name("SECONDS")
+                        .help("Wait the given time between the messages")
+                        .value_parser(parse_duration),
+                ),
+        );
+
+    let args = config::args_with_defaults(&command).unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        std::process::exit(err.exit_code());
+    });
+    let matches = command.get_matches_from(args);
+
+    let options = DisplayOptions {
+        note_naming: NoteNaming {
+            middle_c: match matches
+                .get_one::<String>("middle-c")
+                .expect("Middle C octave missing")
+                .as_str()
+            {
+                "4" => MiddleC::C4,
+                _ => MiddleC::C3,
+            },
+            flats: matches.get_flag("flats"),
+            style: match matches
+                .get_one::<String>("note-style")
+                .expect("Note style missing")
+                .as_str()
+            {
+                "solfege" => NoteStyle::Solfege,
+                "helmholtz" => NoteStyle::Helmholtz,
+                _ => NoteStyle::Scientific,
+            },
+        },
+        numeric_notes: matches.get_flag("numeric-notes"),
+        drum_channel: matches.get_one::<u8>("drum-map").map(|channel| channel - 1),
+        bend_range: matches.get_one::<f64>("bend-semitones").copied(),
+        bend_direction: matches.get_flag("bend-direction"),
+        tuning: if matches.get_flag("freq") {
+            matches.get_one::<f64>("tuning").copied()
+        } else {
+            None
+        },
+        dynamics: matches.get_flag("dynamics"),
+        implicit_note_off: !matches.get_flag("no-implicit-note-off"),
+        color: match matches
+            .get_one::<String>("color")
+            .expect("Color mode missing")
+            .as_str()
+        {
+            "always" => true,
+            "never" => false,
+            _ => {
+                std::io::stdout().is_terminal()
+                    && std::env::var_os("NO_COLOR").map_or(true, |value| value.is_empty())
+            }
+        },
+        timestamp_format: match matches
+            .get_one::<String>("timestamp-format")
+            .expect("Timestamp format missing")
+            .as_str()
+        {
+            _ if matches.get_flag("no-timestamp") => TimestampFormat::None,
+            "wallclock" => TimestampFormat::Wallclock,
+            "delta" => TimestampFormat::Delta,
+            "none" => TimestampFormat::None,
+            _ => TimestampFormat::Raw,
+        },
+        align: matches.get_flag("align"),
+        bars: matches.get_flag("bars"),
+        gm: matches.get_flag("gm"),
+        hex_data: matches.get_flag("hex-data"),
+        pretty_sysex: matches.get_flag("pretty-sysex"),
+        with_raw: matches.get_flag("with-raw"),
+        verify_checksum: matches.get_flag("verify-checksum"),
+        zero_based_channels: matches.get_flag("zero-based-channels"),
+        msb_first: matches.get_flag("msb-first"),
+        group_by_channel: matches.get_flag("group-by-channel"),
+        assume_channel: matches
+            .get_one::<u8>("assume-channel")
+            .map(|channel| channel - 1),
+        song_position: if matches.get_flag("follow-song-position") {
+            matches.get_one::<TimeSignature>("time-signature").copied()
+        } else {
+            None
+        },
+    };
 
-    let matches = command.get_matches();
+    let client_name = matches
+        .get_one::<String>("client-name")
+        .expect("Client name missing");
 
     let result = match matches.subcommand() {
-        Some(("list", _)) => list_ports(),
+        Some(("list", list_matches)) => list_ports(
+            client_name,
+            if list_matches.get_flag("outputs") {
+                PortDirection::Output
+            } else {
+                PortDirection::Input
+            },
+            list_matches.get_flag("json"),
+        ),
+        Some(("replay", replay_matches)) => replay::replay(replay::ReplayArgs {
+            file: replay_matches
+                .get_one::<PathBuf>("file")
+                .expect("File argument missing")
+                .to_owned(),
+            port: replay_matches
+                .get_one::<String>("port")
+                .expect("Port argument missing")
+                .to_owned(),
+            speed: *replay_matches
+                .get_one::<f64>("speed")
+                .expect("Speed argument missing"),
+            quiet: matches.get_flag("quiet"),
+            client_name: client_name.to_string(),
+        }),
+        Some(("send", send_matches)) => send_messages(send_matches, options.zero_based_channels)
+            .and_then(|messages| {
+                send::send(send::SendArgs {
+                    port: send_matches
+                        .get_one::<String>("port")
+                        .expect("Port argument missing")
+                        .to_owned(),
+                    messages,
+                    interval: send_matches.get_one::<Duration>("interval").copied(),
+                    quiet: matches.get_flag("quiet"),
+                    client_name: client_name.to_string(),
+                })
+            }),
+        Some(("decode", decode_matches)) => {
+            offline::decode(decode_matches.get_one::<PathBuf>("file"), &options)
+        }
         _ => {
             let format = match matches
                 .get_one::<String>("format")
@@ -123,169 +1114,867 @@ fn main() {
                 "raw" => DisplayFormat::Raw,
                 "min" => DisplayFormat::Min,
                 "min-hex" => DisplayFormat::MinHex,
+                "json" => DisplayFormat::Json,
+                "csv" => DisplayFormat::Csv,
+                "hexdump" => DisplayFormat::Hexdump,
                 _ => DisplayFormat::Default,
             };
 
-            let mut ignore = MessageIgnore {
-                note: false,
-                poly_pressure: false,
-                control_change: false,
-                program_change: false,
-                channel_pressure: false,
-                pitch_bend: false,
-                sysex: false,
-                mtc_frame: false,
-                song_pos_pointer: false,
-                song_select: false,
-                tune_request: false,
-                clock: false,
-                start: false,
-                continue_: false,
-                stop: false,
-                sensing: false,
-                reset: false,
+            if !matches!(format, DisplayFormat::MinHex) {
+                for id in ["hex-prefix", "hex-separator", "uppercase", "lowercase"] {
+                    if matches.value_source(id) == Some(ValueSource::CommandLine) {
+                        clap::Error::raw(
+                            ErrorKind::ArgumentConflict,
+                            format!("'--{}' can only be used with the min-hex format\n", id),
+                        )
+                        .exit();
+                    }
+                }
+            }
+
+            let default_hex_format = HexFormat::default();
+            let hex_format = HexFormat {
+                prefix: matches
+                    .get_one::<String>("hex-prefix")
+                    .cloned()
+                    .unwrap_or(default_hex_format.prefix),
+                separator: matches
+                    .get_one::<String>("hex-separator")
+                    .cloned()
+                    .unwrap_or(default_hex_format.separator),
+                uppercase: !matches.get_flag("lowercase"),
             };
 
+            let mut ignore = MessageIgnore::new(false);
+
             if let Some(ignores) = matches.get_many::<String>("ignore") {
-                for i in ignores {
-                    match i.as_str() {
-                        "note" => ignore.note = true,
-                        "polyat" => ignore.poly_pressure = true,
-                        "cc" => ignore.control_change = true,
-                        "pc" => ignore.program_change = true,
-                        "at" => ignore.channel_pressure = true,
-                        "pb" => ignore.pitch_bend = true,
-                        "sysex" => ignore.sysex = true,
-                        "clock" => ignore.clock = true,
-                        "sensing" => ignore.sensing = true,
-                        "realtime" => {
-                            ignore.clock = true;
-                            ignore.start = true;
-                            ignore.continue_ = true;
-                            ignore.stop = true;
-                            ignore.sensing = true;
-                            ignore.reset = true;
-                        }
-                        "transport" => {
-                            ignore.start = true;
-                            ignore.continue_ = true;
-                            ignore.stop = true;
-                        }
-                        "system" => {
-                            ignore.sysex = true;
-                            ignore.mtc_frame = true;
-                            ignore.song_pos_pointer = true;
-                            ignore.song_select = true;
-                            ignore.tune_request = true;
-                            ignore.clock = true;
-                            ignore.start = true;
-                            ignore.continue_ = true;
-                            ignore.stop = true;
-                            ignore.sensing = true;
-                            ignore.reset = true;
-                        }
-                        &_ => (),
-                    }
+                for message_type in ignores {
+                    ignore.set(message_type, true);
                 }
             };
 
-            let filter = MessageFilter {
-                channel: if matches.contains_id("channel") {
-                    Some(
-                        matches
-                            .get_one::<u8>("channel")
-                            .expect("Channel argument missing.")
-                            .to_owned(),
+            if let Some(only) = matches.get_many::<String>("only") {
+                ignore = MessageIgnore::new(true);
+                for message_type in only {
+                    ignore.set(message_type, false);
+                }
+            };
+
+            let throttles = matches
+                .get_many::<(String, f64)>("throttle")
+                .map(|throttles| {
+                    throttles
+                        .map(|(message_type, rate)| {
+                            let mut types = MessageIgnore::new(false);
+                            types.set(message_type, true);
+                            Throttle {
+                                types,
+                                interval: (1e6 / rate) as u64,
+                                last_timestamp: None,
+                            }
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            // Channel lists are parsed as 0-16 and checked against the chosen numbering here
+            let first_channel = if options.zero_based_channels { 0 } else { 1 };
+            let invalid_channel = if options.zero_based_channels { 16 } else { 0 };
+            for id in ["channel", "exclude-channel"] {
+                if matches
+                    .get_one::<u128>(id)
+                    .is_some_and(|set| set & (1 << invalid_channel) != 0)
+                {
+                    clap::Error::raw(
+                        ErrorKind::ValueValidation,
+                        format!(
+                            "invalid channel {} for '--{}', must be {}-{}\n",
+                            invalid_channel,
+                            id,
+                            first_channel,
+                            first_channel + 15
+                        ),
                     )
-                } else {
-                    None
+                    .exit();
+                }
+            }
+
+            let filter = MessageFilter {
+                channels: match (
+                    matches.get_one::<u128>("channel"),
+                    matches.get_one::<u128>("exclude-channel"),
+                ) {
+                    (None, None) => None,
+                    (channels, excluded) => {
+                        let channels = channels.copied().unwrap_or(u128::MAX);
+                        let excluded = excluded.copied().unwrap_or(0);
+                        Some(((channels & !excluded) >> first_channel) as u16)
+                    }
                 },
+                note_range: matches.get_one::<(u8, u8)>("filter-note").copied(),
+                controllers: matches.get_one::<u128>("filter-cc").copied(),
+                velocity_range: match (
+                    matches.get_one::<u8>("min-velocity"),
+                    matches.get_one::<u8>("max-velocity"),
+                ) {
+                    (None, None) => None,
+                    (min, max) => Some((min.copied().unwrap_or(0), max.copied().unwrap_or(127))),
+                },
+                velocity_note_off: matches.get_flag("velocity-note-off"),
+                errors_only: matches.get_flag("errors-only"),
+                hex_patterns: matches
+                    .get_many::<HexPattern>("match-hex")
+                    .unwrap_or_default()
+                    .cloned()
+                    .collect(),
             };
 
             let args = MonitorArgs {
-                port: if matches.contains_id("port") {
-                    Some(
-                        matches
-                            .get_one::<u8>("port")
-                            .expect("Port argument missing.")
-                            .to_owned(),
-                    )
-                } else {
-                    None
-                },
+                ports: matches
+                    .get_many::<String>("port")
+                    .map(|ports| ports.cloned().collect())
+                    .unwrap_or_default(),
+                port_names: matches.get_flag("port-names"),
+                virtual_port: matches.get_one::<String>("virtual").cloned(),
+                thru: matches.get_one::<String>("thru").cloned(),
+                thru_all: matches.get_flag("thru-all"),
                 format,
+                annotate: matches.get_flag("annotate"),
+                hex_format,
+                max_line_width: matches.get_one::<usize>("max-line-width").copied(),
+                template: matches.get_one::<String>("template").cloned(),
+                options,
                 ignore,
                 filter,
+                throttles,
+                beep_types: matches.get_many::<String>("beep-on").map(|types| {
+                    let mut beep_types = MessageIgnore::new(false);
+                    for message_type in types {
+                        beep_types.set(message_type, true);
+                    }
+                    beep_types
+                }),
+                nrpn: !matches.get_flag("no-nrpn"),
+                cc14: matches.get_flag("cc14"),
+                bpm: matches.get_flag("bpm"),
+                durations: matches.get_flag("durations"),
+                dedup: matches.get_flag("dedup"),
+                heartbeat: matches.get_one::<Duration>("heartbeat").copied(),
+                stuck_notes: matches.get_one::<Duration>("stuck-notes").copied(),
+                poly_limit: matches.get_one::<usize>("poly-limit").copied(),
+                track_cc: matches.get_flag("track-cc"),
+                velocity_histogram: matches.get_flag("velocity-histogram"),
+                clock_jitter: matches.get_flag("clock-jitter"),
+                channel_activity: matches.get_flag("show-channel-activity"),
+                detect_gaps: matches.get_flag("detect-gaps"),
+                watch_sensing: matches.get_flag("watch-sensing"),
+                reconnect: matches.get_flag("reconnect"),
+                sysex_max: *matches
+                    .get_one::<usize>("sysex-max")
+                    .expect("SysEx max size missing"),
+                count: matches.get_one::<usize>("count").copied(),
+                omit_realtime: matches.get_flag("omit-realtime-from-count"),
+                duration: matches.get_one::<Duration>("duration").copied(),
+                numbering: if matches.get_flag("number-all") {
+                    Some(Numbering::All)
+                } else if matches.get_flag("number") {
+                    Some(Numbering::Shown)
+                } else {
+                    None
+                },
+                relative: matches.get_flag("relative"),
+                stats: matches.get_flag("stats"),
+                output: matches.get_one::<PathBuf>("output").cloned(),
+                split_output: matches.get_one::<PathBuf>("split-output").cloned(),
+                line_buffered: matches.get_flag("line-buffered")
+                    || (!matches.contains_id("flush-interval") && std::io::stdout().is_terminal()),
+                flush_interval: matches
+                    .get_one::<u64>("flush-interval")
+                    .map_or(FLUSH_INTERVAL, |millis| Duration::from_millis(*millis)),
+                smf: matches.get_one::<PathBuf>("smf").cloned(),
+                syx_dir: matches.get_one::<PathBuf>("syx-dir").cloned(),
+                client_name: client_name.to_string(),
+                interactive: matches.get_flag("interactive"),
+                tui: matches.get_flag("tui"),
+                keyboard: matches.get_flag("keyboard"),
                 quiet: matches.get_flag("quiet"),
             };
             monitor(args)
         }
     };
 
-    match result {
-        Ok(_) => (),
-        Err(err) => println!("Error: {}", err),
+    if let Err(err) = result {
+        eprintln!("Error: {}", err);
+        std::process::exit(err.exit_code());
+    }
+}
+
+/// Return the message type values for the ignore and only arguments
+/// Single message types use the short names of their status
+fn message_types() -> Vec<PossibleValue> {
+    let mut message_types = vec![PossibleValue::new("note")];
+
+    message_types.extend(
+        Status::ALL
+            .iter()
+            .filter_map(|status| status.token())
+            .map(PossibleValue::new),
+    );
+
+    message_types.extend([
+        PossibleValue::new("realtime"),
+        PossibleValue::new("transport"),
+        PossibleValue::new("system"),
+    ]);
+
+    message_types
+}
+
+/// Parse a throttle argument like clock:2 into message type and rate
+fn parse_throttle(value: &str) -> Result<(String, f64), String> {
+    let (message_type, rate) = value
+        .split_once(':')
+        .ok_or(format!("invalid throttle \"{}\", must be TYPE:HZ", value))?;
+
+    if !message_types()
+        .iter()
+        .any(|possible| possible.matches(message_type, false))
+    {
+        return Err(format!("invalid message type \"{}\"", message_type));
+    }
+
+    match rate.trim().parse::<f64>() {
+        Ok(rate) if rate > 0.0 => Ok((String::from(message_type), rate)),
+        _ => Err(format!("invalid rate \"{}\"", rate)),
+    }
+}
+
+/// Parse a duration argument in seconds
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    match value.trim().parse::<f64>().map(Duration::try_from_secs_f64) {
+        Ok(Ok(duration)) if !duration.is_zero() => Ok(duration),
+        _ => Err(format!("invalid duration \"{}\"", value)),
+    }
+}
+
+/// Parse a tuning argument like A=432 or 432
+fn parse_tuning(value: &str) -> Result<f64, String> {
+    let frequency = value
+        .strip_prefix("A=")
+        .or_else(|| value.strip_prefix("a="))
+        .unwrap_or(value);
+
+    match frequency.trim().parse::<f64>() {
+        Ok(frequency) if frequency > 0.0 => Ok(frequency),
+        _ => Err(format!("invalid frequency \"{}\"", frequency)),
+    }
+}
+
+/// Parse a time signature argument like 3/4 or 6/8
+fn parse_time_signature(value: &str) -> Result<TimeSignature, String> {
+    let error = || format!("invalid time signature \"{}\"", value);
+    let (beats, beat_value) = value.split_once('/').ok_or_else(error)?;
+
+    match (beats.trim().parse::<u8>(), beat_value.trim().parse::<u8>()) {
+        (Ok(beats), Ok(beat_value)) if beats > 0 && matches!(beat_value, 1 | 2 | 4 | 8 | 16) => {
+            Ok(TimeSignature { beats, beat_value })
+        }
+        _ => Err(error()),
+    }
+}
+
+/// Parse a list of numbers and ranges like 1,7,20-30 into a bitmask
+fn parse_number_set(value: &str, min: u8, max: u8) -> Result<u128, String> {
+    let parse_number = |number: &str| -> Result<u8, String> {
+        match number.trim().parse::<u8>() {
+            Ok(number) if number >= min && number <= max => Ok(number),
+            _ => Err(format!(
+                "invalid number \"{}\", must be {}-{}",
+                number.trim(),
+                min,
+                max
+            )),
+        }
+    };
+
+    let mut set = 0u128;
+
+    for item in value.split(',') {
+        let (low, high) = match item.split_once('-') {
+            Some((low, high)) => (parse_number(low)?, parse_number(high)?),
+            None => {
+                let number = parse_number(item)?;
+                (number, number)
+            }
+        };
+        if low > high {
+            return Err(format!("range start {} is above range end {}", low, high));
+        }
+        for number in low..=high {
+            set |= 1 << number;
+        }
+    }
+
+    Ok(set)
+}
+
+/// Format a bitmask of numbers as list
+fn format_number_set(set: u128) -> String {
+    let numbers: Vec<String> = (0..128)
+        .filter(|number| set & (1 << number) != 0)
+        .map(|number| number.to_string())
+        .collect();
+    numbers.join(",")
+}
+
+/// Parse a note range argument like 36-96, 60- or -72
+fn parse_note_range(value: &str) -> Result<(u8, u8), String> {
+    let parse_note = |note: &str, default: u8| -> Result<u8, String> {
+        if note.is_empty() {
+            return Ok(default);
+        }
+        match note.trim().parse::<u8>() {
+            Ok(note) if note <= 127 => Ok(note),
+            _ => Err(format!("invalid note number \"{}\", must be 0-127", note)),
+        }
+    };
+
+    let (low, high) = match value.split_once('-') {
+        Some((low, high)) => (parse_note(low, 0)?, parse_note(high, 127)?),
+        None => {
+            let note = parse_note(value, 0)?;
+            (note, note)
+        }
+    };
+
+    if low > high {
+        return Err(format!("lower note {} is above upper note {}", low, high));
+    }
+
+    Ok((low, high))
+}
+
+/// Direction of ports to list
+#[derive(Copy, Clone)]
+enum PortDirection {
+    Input,
+    Output,
+}
+
+impl std::fmt::Display for PortDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PortDirection::Input => write!(f, "input"),
+            PortDirection::Output => write!(f, "output"),
+        }
+    }
+}
+
+/// List all available ports of a direction
+fn list_ports(client_name: &str, direction: PortDirection, json: bool) -> Result<(), MidimonError> {
+    let port_names = match direction {
+        PortDirection::Input => port_names(&MidiInput::new(&input_client(client_name))?)?,
+        PortDirection::Output => port_names(&MidiOutput::new(&output_client(client_name))?)?,
+    };
+
+    if json {
+        let ports: Vec<String> = port_names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| format!("{{\"index\":{},\"name\":\"{}\"}}", i, json_escape(name)))
+            .collect();
+        println!("[{}]", ports.join(","));
+        return Ok(());
+    }
+
+    println!("Available {} ports:", direction);
+
+    for (i, name) in port_names.iter().enumerate() {
+        println!("  ({}) {}", i, name);
+    }
+
+    Ok(())
+}
+
+/// Return the port shown in display lines, the id followed by the name padded or truncated
+/// to a fixed width if enabled
+fn port_label(port_id: usize, port_name: &str, show_name: bool) -> String {
+    if show_name {
+        format!(
+            "{} {:<width$.width$}",
+            port_id,
+            port_name,
+            width = PORT_NAME_WIDTH
+        )
+    } else {
+        port_id.to_string()
+    }
+}
+
+/// Return the client name for input connections
+fn input_client(client_name: &str) -> String {
+    format!("{} input", client_name)
+}
+
+/// Return the messages of the send subcommand in the order of the arguments
+/// Channels are checked against the chosen numbering, the other values are checked by the parser.
+fn send_messages(
+    matches: &ArgMatches,
+    zero_based_channels: bool,
+) -> Result<Vec<MidiMessage>, MidimonError> {
+    let first_channel = if zero_based_channels { 0 } else { 1 };
+    let mut messages = Vec::new();
+
+    for (id, status) in [
+        ("note-on", Status::NoteOn),
+        ("note-off", Status::NoteOff),
+        ("cc", Status::ControlChange),
+        ("program", Status::ProgramChange),
+    ] {
+        let (Some(occurrences), Some(indices)) =
+            (matches.get_occurrences::<u8>(id), matches.indices_of(id))
+        else {
+            continue;
+        };
+
+        let values_per_message = if status == Status::ProgramChange {
+            2
+        } else {
+            3
+        };
+        for (values, index) in occurrences.zip(indices.step_by(values_per_message)) {
+            let values: Vec<u8> = values.copied().collect();
+            let channel = values[0];
+            if !(first_channel..first_channel + 16).contains(&channel) {
+                return Err(MidimonError::InvalidArgument(format!(
+                    "Invalid channel {} for --{}, must be {}-{}.",
+                    channel,
+                    id,
+                    first_channel,
+                    first_channel + 15
+                )));
+            }
+            let message =
+                MidiMessage::from_channel_status(status, channel - first_channel, &values[1..]);
+            messages.push((index, message));
+        }
+    }
+
+    if let (Some(bytes), Some(indices)) = (
+        matches.get_many::<Vec<u8>>("bytes"),
+        matches.indices_of("bytes"),
+    ) {
+        for (bytes, index) in bytes.zip(indices) {
+            messages.push((index, MidiMessage::from_array(bytes)));
+        }
+    }
+
+    messages.sort_by_key(|(index, _)| *index);
+
+    Ok(messages.into_iter().map(|(_, message)| message).collect())
+}
+
+/// Return the client name for output connections
+fn output_client(client_name: &str) -> String {
+    format!("{} output", client_name)
+}
+
+/// Return the names of all ports in the order of their ids
+fn port_names<T: MidiIO>(midi_io: &T) -> Result<Vec<String>, MidimonError> {
+    midi_io
+        .ports()
+        .iter()
+        .map(|port| Ok(midi_io.port_name(port)?))
+        .collect()
+}
+
+/// Escape a string for use in JSON output
+fn json_escape(value: &str) -> String {
+    let mut escaped = String::new();
+
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+// Monitor function arguments
+struct MonitorArgs {
+    ports: Vec<String>,
+    port_names: bool,
+    virtual_port: Option<String>,
+    thru: Option<String>,
+    thru_all: bool,
+    format: DisplayFormat,
+    annotate: bool,
+    hex_format: HexFormat,
+    max_line_width: Option<usize>,
+    template: Option<String>,
+    options: DisplayOptions,
+    ignore: MessageIgnore,
+    filter: MessageFilter,
+    throttles: Vec<Throttle>,
+    beep_types: Option<MessageIgnore>,
+    nrpn: bool,
+    cc14: bool,
+    bpm: bool,
+    durations: bool,
+    dedup: bool,
+    heartbeat: Option<Duration>,
+    stuck_notes: Option<Duration>,
+    poly_limit: Option<usize>,
+    track_cc: bool,
+    velocity_histogram: bool,
+    clock_jitter: bool,
+    channel_activity: bool,
+    detect_gaps: bool,
+    watch_sensing: bool,
+    reconnect: bool,
+    sysex_max: usize,
+    count: Option<usize>,
+    omit_realtime: bool,
+    duration: Option<Duration>,
+    numbering: Option<Numbering>,
+    relative: bool,
+    stats: bool,
+    output: Option<PathBuf>,
+    split_output: Option<PathBuf>,
+    line_buffered: bool,
+    flush_interval: Duration,
+    smf: Option<PathBuf>,
+    syx_dir: Option<PathBuf>,
+    client_name: String,
+    interactive: bool,
+    tui: bool,
+    keyboard: bool,
+    quiet: bool,
+}
+
+/// Monitor one or multiple input ports
+fn monitor(args: MonitorArgs) -> Result<(), MidimonError> {
+    let running = Arc::new(AtomicBool::new(true));
+    let handler_running = running.clone();
+    ctrlc::set_handler(move || handler_running.store(false, Ordering::SeqCst))?;
+
+    let stats = Arc::new(Mutex::new(Stats {
+        omit_realtime: args.omit_realtime,
+        assume_channel: args.options.assume_channel,
+        ..Default::default()
+    }));
+    let note_watch = if args.stuck_notes.is_some() || args.poly_limit.is_some() {
+        Some(Arc::new(Mutex::new(NoteWatch::new(
+            args.stuck_notes.map(|duration| duration.as_micros() as u64),
+            args.poly_limit,
+        ))))
+    } else {
+        None
+    };
+    let cc_tracker = if args.track_cc {
+        Some(Arc::new(Mutex::new(CcTracker {
+            assume_channel: args.options.assume_channel,
+            ..Default::default()
+        })))
+    } else {
+        None
+    };
+    let last_beep = Arc::new(Mutex::new(None));
+    let velocities = if args.velocity_histogram {
+        Some(Arc::new(Mutex::new(VelocityHistogram::default())))
+    } else {
+        None
+    };
+    let clock_jitter = if args.clock_jitter {
+        Some(Arc::new(Mutex::new(ClockJitter::default())))
+    } else {
+        None
+    };
+    let message_count = Arc::new(AtomicUsize::new(0));
+    let received_count = Arc::new(AtomicUsize::new(0));
+    let time_origin = if args.relative {
+        Some(Arc::new(Mutex::new(None)))
+    } else {
+        None
+    };
+    let previous_timestamp = Arc::new(Mutex::new(None));
+    let start = Instant::now();
+
+    if args.dedup && matches!(args.format, DisplayFormat::Json | DisplayFormat::Csv) {
+        return Err(MidimonError::InvalidArgument(String::from(
+            "--dedup can't be used with the json and csv formats",
+        )));
+    }
+
+    if args.annotate && !matches!(args.format, DisplayFormat::Min | DisplayFormat::MinHex) {
+        return Err(MidimonError::InvalidArgument(String::from(
+            "--annotate can only be used with the min and min-hex formats",
+        )));
+    }
+
+    if args.max_line_width.is_some()
+        && !matches!(
+            args.format,
+            DisplayFormat::Raw | DisplayFormat::Min | DisplayFormat::MinHex
+        )
+    {
+        return Err(MidimonError::InvalidArgument(String::from(
+            "--max-line-width can only be used with the raw, min and min-hex formats",
+        )));
+    }
+
+    if args.numbering.is_some() && matches!(args.format, DisplayFormat::Json | DisplayFormat::Csv) {
+        return Err(MidimonError::InvalidArgument(String::from(
+            "--number can't be used with the json and csv formats",
+        )));
+    }
+
+    let midi_in = MidiInput::new(&input_client(&args.client_name))?;
+
+    let mut virtual_connection = None;
+    let mut port_connections = Vec::<PortConnection>::new();
+
+    let output: OutputWriter = Arc::new(Mutex::new(DisplayWriter {
+        stdout: BufWriter::new(std::io::stdout()),
+        file: match &args.output {
+            Some(path) => Some(BufWriter::new(File::create(path)?)),
+            None => None,
+        },
+        split_dir: args.split_output.clone(),
+        port_files: BTreeMap::new(),
+        header: None,
+        line_buffered: args.line_buffered,
+        max_width: args.max_line_width,
+        separator: match args.format {
+            DisplayFormat::MinHex => args.hex_format.separator.clone(),
+            _ => String::from(", "),
+        },
+    }));
+
+    // The header is written before connecting the ports so that it precedes all rows
+    if let DisplayFormat::Csv = args.format {
+        output
+            .lock()
+            .unwrap()
+            .write_header(csv_header(), !args.quiet)?;
+    }
+
+    let thru = match &args.thru {
+        Some(port) => {
+            let midi_out = MidiOutput::new(&output_client(&args.client_name))?;
+            let port_id = select_ports(&midi_out, port, "output")?[0];
+            let out_port =
+                midi_out
+                    .ports()
+                    .get(port_id)
+                    .cloned()
+                    .ok_or(MidimonError::PortNotFound(format!(
+                        "No output port with id {}.",
+                        port_id
+                    )))?;
+            let port_name = midi_out.port_name(&out_port)?;
+            let connection: ThruConnection =
+                Arc::new(Mutex::new(midi_out.connect(&out_port, "thru")?));
+            Some((connection, port_id, port_name))
+        }
+        None => None,
+    };
+
+    let smf = args
+        .smf
+        .as_ref()
+        .map(|_| Arc::new(Mutex::new(SmfRecorder::new())));
+
+    if let Some(interval) = args.heartbeat {
+        let heartbeat_count = received_count.clone();
+        let heartbeat_running = running.clone();
+        std::thread::spawn(move || heartbeat(interval, heartbeat_count, heartbeat_running));
     }
-}
 
-/// List all available input ports
-fn list_ports() -> Result<(), Box<dyn std::error::Error>> {
-    println!("Available input ports:");
+    let sensing = if args.watch_sensing {
+        let sensing: SensingTimes = Arc::new(Mutex::new(BTreeMap::new()));
+        let watcher_sensing = sensing.clone();
+        let watcher_running = running.clone();
+        std::thread::spawn(move || watch_sensing(watcher_sensing, watcher_running));
+        Some(sensing)
+    } else {
+        None
+    };
 
-    let midi_in = MidiInput::new("midimon input")?;
+    let paused = Arc::new(AtomicBool::new(false));
 
-    for (i, p) in midi_in.ports().iter().enumerate() {
-        println!("  ({}) {}", i, midi_in.port_name(p)?);
-    }
+    let keyboard = if args.keyboard {
+        Some(Arc::new(Mutex::new(KeyboardState {
+            held: HeldNotes::default(),
+            octave: KEYBOARD_START_OCTAVE,
+        })))
+    } else {
+        None
+    };
 
-    Ok(())
-}
+    let (tui_sender, tui_receiver) = if args.tui {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        (Some(sender), Some(receiver))
+    } else {
+        (None, None)
+    };
 
-// Monitor function arguments
-struct MonitorArgs {
-    port: Option<u8>,
-    format: DisplayFormat,
-    ignore: MessageIgnore,
-    filter: MessageFilter,
-    quiet: bool,
-}
+    if let Some(dir) = &args.split_output {
+        std::fs::create_dir_all(dir)?;
+    }
 
-/// Monitor one or multiple input ports
-#[allow(unreachable_code)]
-fn monitor(args: MonitorArgs) -> Result<(), Box<dyn std::error::Error>> {
-    let midi_in = MidiInput::new("midimon input")?;
+    if let Some(dir) = &args.syx_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+    let syx_count = Arc::new(AtomicUsize::new(0));
 
-    type Connection = Result<MidiInputConnection<ReceiveArgs>, ConnectError<MidiInput>>;
+    let dedup: Option<RepeatState> = if args.dedup {
+        Some(Arc::new(Mutex::new(None)))
+    } else {
+        None
+    };
 
-    let mut connections = Vec::<Connection>::new();
+    let receive_args = |port_id, port_name: &str| ReceiveArgs {
+        port_id,
+        port_label: port_label(port_id, port_name, args.port_names),
+        format: args.format,
+        annotate: args.annotate,
+        hex_format: args.hex_format.clone(),
+        template: args.template.clone(),
+        options: args.options,
+        ignore: args.ignore,
+        filter: args.filter.clone(),
+        throttles: args.throttles.clone(),
+        beep_types: args.beep_types,
+        last_beep: last_beep.clone(),
+        nrpn: args.nrpn,
+        parameters: [ParameterState::default(); 16],
+        cc14: args.cc14,
+        cc14_state: [Cc14State::default(); 16],
+        banks: [BankState::default(); 16],
+        bpm: args.bpm,
+        clock: ClockState::default(),
+        durations: if args.durations {
+            Some(NoteDurations::default())
+        } else {
+            None
+        },
+        mtc: MtcState::default(),
+        mtc_sequence: if args.detect_gaps {
+            Some(MtcSequence::default())
+        } else {
+            None
+        },
+        sysex_buffer: Vec::new(),
+        sysex_timestamp: 0,
+        sysex_max: args.sysex_max,
+        stats: stats.clone(),
+        cc_tracker: cc_tracker.clone(),
+        velocities: velocities.clone(),
+        clock_jitter: clock_jitter.clone(),
+        note_watch: note_watch.clone(),
+        start,
+        stats_only: args.stats,
+        running: running.clone(),
+        count: args.count,
+        omit_realtime: args.omit_realtime,
+        message_count: message_count.clone(),
+        numbering: args.numbering,
+        received_count: received_count.clone(),
+        time_origin: time_origin.clone(),
+        previous_timestamp: previous_timestamp.clone(),
+        output: output.clone(),
+        thru: thru.as_ref().map(|(connection, _, _)| connection.clone()),
+        thru_all: args.thru_all,
+        smf: smf.clone(),
+        sensing: sensing.clone(),
+        paused: paused.clone(),
+        tui: tui_sender.clone(),
+        keyboard: keyboard.clone(),
+        syx_dir: args.syx_dir.clone(),
+        syx_count: syx_count.clone(),
+        dedup: dedup.clone(),
+    };
 
     let show_info = !args.quiet;
 
-    if show_info {
-        println!("Active input ports:");
-    }
-
-    for (i, in_port) in midi_in.ports().iter().enumerate() {
-        let midi_in = MidiInput::new("midimon input")?;
-        let port_name = midi_in.port_name(in_port)?;
-        let add_connection = if let Some(port_id) = args.port {
-            port_id == i as u8
+    if let Some(name) = &args.virtual_port {
+        virtual_connection = Some(create_virtual_input(
+            &args.client_name,
+            name,
+            receive_args(0, name),
+        )?);
+        if show_info {
+            println!("Virtual input port:");
+            println!("  (0) {}", name);
+        }
+    } else {
+        let port_ids = if args.ports.is_empty() {
+            (0..midi_in.port_count()).collect()
         } else {
-            true
+            let mut port_ids = Vec::new();
+            for port in &args.ports {
+                port_ids.extend(select_ports(&midi_in, port, "input")?);
+            }
+            port_ids
         };
 
-        if add_connection {
-            if show_info {
-                println!("  ({}) {}", i, port_name);
+        if show_info {
+            println!("Active input ports:");
+        }
+
+        for (i, in_port) in midi_in.ports().iter().enumerate() {
+            let midi_in = MidiInput::new(&input_client(&args.client_name))?;
+            let port_name = midi_in.port_name(in_port)?;
+            let add_connection = port_ids.contains(&i);
+
+            if add_connection {
+                if show_info {
+                    println!("  ({}) {}", i, port_name);
+                }
+
+                // Failed ports are kept to be retried when reconnecting is enabled
+                let connection = match midi_in.connect(
+                    in_port,
+                    "input monitor",
+                    on_receive,
+                    receive_args(i, &port_name),
+                ) {
+                    Ok(connection) => Some(connection),
+                    Err(err) => {
+                        eprintln!(
+                            "Warning: Can't connect to input port ({}) {}: {}",
+                            i, port_name, err
+                        );
+                        None
+                    }
+                };
+                port_connections.push(PortConnection {
+                    port_id: i,
+                    name: port_name,
+                    connection,
+                });
             }
+        }
 
-            let receive_args = ReceiveArgs {
-                port_id: i,
-                format: args.format,
-                ignore: args.ignore,
-                filter: args.filter,
-            };
-            connections.push(midi_in.connect(in_port, "input monitor", on_receive, receive_args));
+        // A port id without a port or an empty port list would leave nothing to monitor
+        if port_connections.is_empty() {
+            return Err(MidimonError::PortNotFound(String::from(
+                "No matching input ports found; use `midimon list` to show the available ports.",
+            )));
+        }
+
+        if port_connections
+            .iter()
+            .all(|port_connection| port_connection.connection.is_none())
+        {
+            return Err(MidimonError::Connection(String::from(
+                "None of the selected input ports could be connected.",
+            )));
         }
     }
 
@@ -304,12 +1993,18 @@ fn monitor(args: MonitorArgs) -> Result<(), Box<dyn std::error::Error>> {
         if args.ignore.program_change {
             ignore_info.push("Program Change".to_string());
         }
+        if args.ignore.channel_pressure {
+            ignore_info.push("Channel Pressure".to_string());
+        }
         if args.ignore.pitch_bend {
             ignore_info.push("Pitch Bend".to_string());
         }
         if args.ignore.sysex {
             ignore_info.push("Sysex".to_string());
         }
+        if args.ignore.eox {
+            ignore_info.push("End of Exclusive".to_string());
+        }
         if args.ignore.mtc_frame {
             ignore_info.push("MTC Quarter Frame".to_string());
         }
@@ -345,205 +2040,831 @@ fn monitor(args: MonitorArgs) -> Result<(), Box<dyn std::error::Error>> {
             println!("Ignoring {}", ignore_info.join(", "));
         }
 
-        if let Some(channel) = args.filter.channel {
-            println!("Using channel filter {}", channel);
+        if let Some(channels) = args.filter.channels {
+            println!(
+                "Using channel filter {}",
+                format_number_set(
+                    (channels as u128) << u8::from(!args.options.zero_based_channels)
+                )
+            );
+        }
+
+        if let Some((low, high)) = args.filter.note_range {
+            println!("Using note filter {}-{}", low, high);
+        }
+
+        if args.filter.errors_only {
+            println!("Showing only unknown or malformed messages");
+        }
+
+        if !args.filter.hex_patterns.is_empty() {
+            let patterns: Vec<String> = args
+                .filter
+                .hex_patterns
+                .iter()
+                .map(HexPattern::to_string)
+                .collect();
+            println!("Using hex pattern filter {}", patterns.join(", "));
+        }
+
+        if let Some((min, max)) = args.filter.velocity_range {
+            println!("Using velocity filter {}-{}", min, max);
+        }
+
+        if let Some(controllers) = args.filter.controllers {
+            println!("Using controller filter {}", format_number_set(controllers));
+        }
+
+        if let Some(path) = &args.output {
+            println!("Writing messages to {}", path.display());
+        }
+
+        if let Some(dir) = &args.split_output {
+            println!("Writing messages of each port to {}", dir.display());
+        }
+
+        if let Some(path) = &args.smf {
+            println!("Recording messages to {}", path.display());
+        }
+
+        if let Some(dir) = &args.syx_dir {
+            println!("Writing SysEx messages to {}", dir.display());
+        }
+
+        if let Some((_, port_id, port_name)) = &thru {
+            println!("Forwarding messages to ({}) {}", port_id, port_name);
         }
 
         println!("Listening... Press Ctrl-C to exit.");
     }
 
-    loop {
-        std::thread::sleep(std::time::Duration::from_millis(10));
+    let dashboard = tui_receiver.map(|receiver| {
+        let dashboard_running = running.clone();
+        let note_naming = args.options.note_naming;
+        std::thread::spawn(move || dashboard::run(receiver, dashboard_running, note_naming))
+    });
+
+    let raw_terminal = if args.interactive {
+        let raw_terminal = interactive::RawTerminal::enable()?;
+        interactive::spawn_key_handler(paused.clone(), stats.clone(), start, args.options);
+        if show_info {
+            println!("Keys: space to pause/resume, c to clear, s to show statistics.");
+        }
+        Some(raw_terminal)
+    } else {
+        None
+    };
+
+    let mut last_port_check = Instant::now();
+    let mut last_flush = Instant::now();
+
+    while running.load(Ordering::SeqCst) {
+        std::thread::sleep(Duration::from_millis(10));
+
+        if args
+            .duration
+            .is_some_and(|duration| start.elapsed() >= duration)
+        {
+            running.store(false, Ordering::SeqCst);
+            break;
+        }
+
+        if args.reconnect && last_port_check.elapsed() >= RECONNECT_INTERVAL {
+            check_ports(&args.client_name, &mut port_connections, &receive_args);
+            last_port_check = Instant::now();
+        }
+
+        if let Some(dedup) = &dedup {
+            let mut pending = dedup.lock().unwrap();
+            if pending
+                .as_ref()
+                .is_some_and(|repeat| repeat.last_time.elapsed() >= DEDUP_TIMEOUT)
+            {
+                if let Some(repeat) = pending.take() {
+                    write_output(&output, repeat.port_id, &repeat.line());
+                }
+            }
+        }
+
+        if let Some(note_watch) = &note_watch {
+            let elapsed = start.elapsed().as_micros() as u64;
+            for (channel, note, duration) in note_watch.lock().unwrap().stuck_notes(elapsed) {
+                let name = MidiMessage::from_array(&[Status::NoteOn as u8 | channel, note, 127])
+                    .note_name_with(&args.options.note_naming)
+                    .unwrap_or_default();
+                eprintln!(
+                    "Warning: Note {} ({}) on channel {} held for {:.1} s, possibly stuck",
+                    note,
+                    name,
+                    channel + u8::from(!args.options.zero_based_channels),
+                    duration as f64 / 1e6
+                );
+            }
+        }
+
+        if last_flush.elapsed() >= args.flush_interval {
+            output.lock().unwrap().flush();
+            last_flush = Instant::now();
+        }
+    }
+
+    drop(virtual_connection);
+    drop(port_connections);
+    drop(raw_terminal);
+
+    if let Some(dashboard) = dashboard {
+        dashboard.join().ok();
+    }
+
+    // Keep the last keyboard line
+    if args.keyboard {
+        println!();
+    }
+
+    if let Some(repeat) = dedup.and_then(|dedup| dedup.lock().unwrap().take()) {
+        write_output(&output, repeat.port_id, &repeat.line());
+    }
+
+    output.lock().unwrap().flush();
+
+    if let (Some(path), Some(smf)) = (&args.smf, &smf) {
+        smf.lock().unwrap().write(&mut File::create(path)?)?;
+    }
+
+    if show_info || args.stats {
+        println!();
+        println!(
+            "{}",
+            stats
+                .lock()
+                .unwrap()
+                .summary(start.elapsed(), args.stats, &args.options)
+        );
+    }
+
+    if let Some(cc_tracker) = &cc_tracker {
+        println!();
+        println!("{}", cc_tracker.lock().unwrap().table(&args.options));
+    }
+
+    if let Some(velocities) = &velocities {
+        println!();
+        println!("{}", velocities.lock().unwrap().histogram());
+    }
+
+    if let Some(clock_jitter) = &clock_jitter {
+        println!();
+        println!("{}", clock_jitter.lock().unwrap().summary());
+    }
+
+    if args.channel_activity {
+        println!();
+        println!("{}", stats.lock().unwrap().channel_activity(&args.options));
     }
 
     Ok(())
 }
 
+/// Close connections to ports that disappeared and reconnect ports that are available again
+fn check_ports(
+    client_name: &str,
+    port_connections: &mut [PortConnection],
+    receive_args: &dyn Fn(usize, &str) -> ReceiveArgs,
+) {
+    let Ok(midi_in) = MidiInput::new(&input_client(client_name)) else {
+        return;
+    };
+
+    let port_names: Vec<String> = midi_in
+        .ports()
+        .iter()
+        .filter_map(|port| midi_in.port_name(port).ok())
+        .collect();
+
+    for port_connection in port_connections.iter_mut() {
+        let available = port_names.contains(&port_connection.name);
+
+        if !available {
+            if let Some(connection) = port_connection.connection.take() {
+                connection.close();
+                eprintln!(
+                    "Port ({}) {} disconnected, waiting for reconnect",
+                    port_connection.port_id, port_connection.name
+                );
+            }
+            continue;
+        }
+
+        if port_connection.connection.is_some() {
+            continue;
+        }
+
+        let Ok(midi_in) = MidiInput::new(&input_client(client_name)) else {
+            continue;
+        };
+        let in_port = midi_in
+            .ports()
+            .into_iter()
+            .find(|port| midi_in.port_name(port).ok().as_ref() == Some(&port_connection.name));
+
+        if let Some(in_port) = in_port {
+            if let Ok(connection) = midi_in.connect(
+                &in_port,
+                "input monitor",
+                on_receive,
+                receive_args(port_connection.port_id, &port_connection.name),
+            ) {
+                port_connection.connection = Some(connection);
+                eprintln!(
+                    "Port ({}) {} reconnected",
+                    port_connection.port_id, port_connection.name
+                );
+            }
+        }
+    }
+}
+
+/// Create a virtual input port that other applications can connect to
+#[cfg(unix)]
+fn create_virtual_input(
+    client_name: &str,
+    name: &str,
+    receive_args: ReceiveArgs,
+) -> Result<Connection, MidimonError> {
+    use midir::os::unix::VirtualInput;
+
+    let midi_in = MidiInput::new(&input_client(client_name))?;
+    Ok(midi_in.create_virtual(name, on_receive, receive_args))
+}
+
+/// Create a virtual input port that other applications can connect to
+#[cfg(not(unix))]
+fn create_virtual_input(
+    _client_name: &str,
+    _name: &str,
+    _receive_args: ReceiveArgs,
+) -> Result<Connection, MidimonError> {
+    Err(MidimonError::Connection(String::from(
+        "Virtual ports are not supported on this platform",
+    )))
+}
+
+/// Return the current time of day in microseconds since midnight in local time
+fn wallclock_timestamp() -> u64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    local_time_of_day(now.as_secs()) * 1_000_000 + now.subsec_micros() as u64
+}
+
+/// Return the seconds since midnight in local time for seconds since the Unix epoch
+#[cfg(unix)]
+fn local_time_of_day(seconds: u64) -> u64 {
+    let time = seconds as libc::time_t;
+
+    // SAFETY: A zeroed `tm` is a valid value, localtime_r is thread-safe and only writes to it
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return seconds % 86400;
+    }
+
+    tm.tm_hour as u64 * 3600 + tm.tm_min as u64 * 60 + tm.tm_sec as u64
+}
+
+/// Return the seconds since midnight for seconds since the Unix epoch
+/// The local time zone is not available, so UTC is used on this platform.
+#[cfg(not(unix))]
+fn local_time_of_day(seconds: u64) -> u64 {
+    seconds % 86400
+}
+
+/// Print a status line at the given interval until monitoring is stopped
+/// The line is left out if messages were received since the last interval
+fn heartbeat(interval: Duration, received_count: Arc<AtomicUsize>, running: Arc<AtomicBool>) {
+    let mut last_beat = Instant::now();
+    let mut last_count = received_count.load(Ordering::SeqCst);
+
+    while running.load(Ordering::SeqCst) {
+        std::thread::sleep(Duration::from_millis(10));
+
+        if last_beat.elapsed() < interval {
+            continue;
+        }
+        last_beat = Instant::now();
+
+        let count = received_count.load(Ordering::SeqCst);
+        if count == last_count {
+            eprintln!(
+                "{}  Still listening ({} messages seen)",
+                format_timestamp(wallclock_timestamp(), TimestampFormat::Wallclock),
+                count
+            );
+        }
+        last_count = count;
+    }
+}
+
+/// Check the ports sending active sensing for timeouts until monitoring is stopped
+/// A warning is shown once per timeout, watching restarts with the next active sensing message
+fn watch_sensing(sensing: SensingTimes, running: Arc<AtomicBool>) {
+    while running.load(Ordering::SeqCst) {
+        std::thread::sleep(Duration::from_millis(10));
+
+        sensing.lock().unwrap().retain(|port_id, last_message| {
+            let silent = last_message.elapsed() > SENSING_TIMEOUT;
+            if silent {
+                eprintln!(
+                    "Warning: No message from port {} for {} ms after active sensing, device silent or possibly disconnected",
+                    port_id,
+                    SENSING_TIMEOUT.as_millis()
+                );
+            }
+            !silent
+        });
+    }
+}
+
 /// Arguments for on_receive() callback function
 struct ReceiveArgs {
     port_id: usize,
+    /// Port shown in the display lines, the id optionally followed by the name
+    port_label: String,
     format: DisplayFormat,
+    annotate: bool,
+    hex_format: HexFormat,
+    template: Option<String>,
+    options: DisplayOptions,
     ignore: MessageIgnore,
     filter: MessageFilter,
+    throttles: Vec<Throttle>,
+    beep_types: Option<MessageIgnore>,
+    last_beep: Arc<Mutex<Option<Instant>>>,
+    nrpn: bool,
+    parameters: [ParameterState; 16],
+    cc14: bool,
+    cc14_state: [Cc14State; 16],
+    banks: [BankState; 16],
+    bpm: bool,
+    clock: ClockState,
+    durations: Option<NoteDurations>,
+    mtc: MtcState,
+    mtc_sequence: Option<MtcSequence>,
+    sysex_buffer: Vec<u8>,
+    sysex_timestamp: u64,
+    sysex_max: usize,
+    stats: Arc<Mutex<Stats>>,
+    cc_tracker: Option<Arc<Mutex<CcTracker>>>,
+    velocities: Option<Arc<Mutex<VelocityHistogram>>>,
+    clock_jitter: Option<Arc<Mutex<ClockJitter>>>,
+    note_watch: Option<Arc<Mutex<NoteWatch>>>,
+    start: Instant,
+    stats_only: bool,
+    running: Arc<AtomicBool>,
+    count: Option<usize>,
+    omit_realtime: bool,
+    message_count: Arc<AtomicUsize>,
+    numbering: Option<Numbering>,
+    received_count: Arc<AtomicUsize>,
+    time_origin: Option<Arc<Mutex<Option<u64>>>>,
+    previous_timestamp: Arc<Mutex<Option<u64>>>,
+    output: OutputWriter,
+    thru: Option<ThruConnection>,
+    thru_all: bool,
+    smf: Option<Arc<Mutex<SmfRecorder>>>,
+    sensing: Option<SensingTimes>,
+    paused: Arc<AtomicBool>,
+    tui: Option<Sender<Vec<u8>>>,
+    keyboard: Option<Arc<Mutex<KeyboardState>>>,
+    syx_dir: Option<PathBuf>,
+    syx_count: Arc<AtomicUsize>,
+    dedup: Option<RepeatState>,
 }
 
 /// Receive callback function
+/// SysEx messages split across multiple callbacks are reassembled before processing
 fn on_receive(timestamp: u64, message: &[u8], args: &mut ReceiveArgs) {
+    if message.is_empty() {
+        return;
+    }
+
+    if args.thru_all {
+        send_thru(args, message);
+    }
+
+    // Once active sensing was received, any message resets the timeout
+    if let Some(sensing) = &args.sensing {
+        let mut sensing = sensing.lock().unwrap();
+        if message[0] == Status::ActiveSensing as u8 || sensing.contains_key(&args.port_id) {
+            sensing.insert(args.port_id, Instant::now());
+        }
+    }
+
+    // Realtime messages may be interleaved with SysEx fragments
+    if !args.sysex_buffer.is_empty() && message[0] < Status::TimingClock as u8 {
+        if message[0] < 0x80 || message[0] == Status::EndOfExclusive as u8 {
+            args.sysex_buffer.extend_from_slice(message);
+            if message.contains(&(Status::EndOfExclusive as u8)) {
+                let sysex = std::mem::take(&mut args.sysex_buffer);
+                process_message(args.sysex_timestamp, &sysex, args);
+            } else if args.sysex_buffer.len() > args.sysex_max {
+                eprintln!(
+                    "Warning: SysEx message exceeds {} bytes, flushing incomplete data",
+                    args.sysex_max
+                );
+                flush_sysex(args);
+            }
+            return;
+        }
+
+        // Any other status byte terminates an unfinished SysEx message
+        eprintln!("Warning: SysEx message interrupted, flushing incomplete data");
+        flush_sysex(args);
+    }
+
+    if message[0] == Status::SystemExclusive as u8
+        && !message.contains(&(Status::EndOfExclusive as u8))
+    {
+        args.sysex_buffer = Vec::from(message);
+        args.sysex_timestamp = timestamp;
+        return;
+    }
+
+    process_message(timestamp, message, args);
+}
+
+/// Process incomplete SysEx data and reset the reassembly buffer
+fn flush_sysex(args: &mut ReceiveArgs) {
+    let sysex = std::mem::take(&mut args.sysex_buffer);
+    process_message(args.sysex_timestamp, &sysex, args);
+}
+
+/// Filter and display a complete message
+fn process_message(timestamp: u64, message: &[u8], args: &mut ReceiveArgs) {
     let status = if message[0] >= 0xF0 {
         message[0]
     } else {
         message[0] & 0xF0
     };
 
-    if args.ignore.note && (status == Status::NoteOff as u8 || status == Status::NoteOn as u8) {
-        return;
+    args.stats.lock().unwrap().add(message);
+
+    if let Some(cc_tracker) = &args.cc_tracker {
+        cc_tracker.lock().unwrap().add(message);
     }
 
-    if args.ignore.poly_pressure && (status == Status::PolyKeyPressure as u8) {
-        return;
+    if let Some(velocities) = &args.velocities {
+        velocities.lock().unwrap().add(message);
     }
 
-    if args.ignore.control_change && (status == Status::ControlChange as u8) {
-        return;
+    if let Some(clock_jitter) = &args.clock_jitter {
+        clock_jitter
+            .lock()
+            .unwrap()
+            .add(args.port_id, message, timestamp);
     }
 
-    if args.ignore.program_change && (status == Status::ProgramChange as u8) {
-        return;
+    // Tempo and note durations are measured on all messages so that filtering and throttling
+    // don't skew them
+    let tempo = if args.bpm && status == Status::TimingClock as u8 {
+        args.clock.tick(timestamp)
+    } else {
+        None
+    };
+    let duration = args
+        .durations
+        .as_mut()
+        .and_then(|durations| durations.process(&MidiMessage::from_array(message), timestamp));
+
+    if args.beep_types.is_some_and(|types| types.matches(status)) {
+        let mut last_beep = args.last_beep.lock().unwrap();
+        if last_beep.map_or(true, |last| last.elapsed() >= BEEP_INTERVAL) {
+            eprint!("\x07");
+            *last_beep = Some(Instant::now());
+        }
     }
 
-    if args.ignore.channel_pressure && (status == Status::ChannelPressure as u8) {
-        return;
+    // Held notes are timed with the session clock to compare them with the current time later
+    if let Some(note_watch) = &args.note_watch {
+        let elapsed = args.start.elapsed().as_micros() as u64;
+        if let Some(count) = note_watch
+            .lock()
+            .unwrap()
+            .process(&MidiMessage::from_array(message), elapsed)
+        {
+            eprintln!(
+                "Warning: {} notes held at the same time, possibly stuck notes",
+                count
+            );
+        }
     }
 
-    if args.ignore.pitch_bend && (status == Status::PitchBend as u8) {
-        return;
+    let received_no = args.received_count.fetch_add(1, Ordering::SeqCst) + 1;
+
+    if status == Status::SystemExclusive as u8 {
+        write_syx(args, message);
     }
 
-    if args.ignore.sysex && (status == Status::SystemExclusive as u8) {
-        return;
+    if let Some(sequence) = &mut args.mtc_sequence {
+        if let Some((field, _)) = MidiMessage::from_array(message).mtc_piece() {
+            if let Some(skipped) = sequence.piece(field) {
+                eprintln!(
+                    "Warning: Possible dropped message on port {}, {} MTC quarter frame(s) skipped before {}",
+                    args.port_id, skipped, field
+                );
+            }
+        }
     }
 
-    if args.ignore.mtc_frame && (status == Status::MtcQuarterFrame as u8) {
-        return;
+    if status == Status::ControlChange as u8 && message.len() >= 3 {
+        args.banks[(message[0] & 0x0F) as usize].process(message[1], message[2]);
     }
 
-    if args.ignore.song_pos_pointer && (status == Status::SongPositionPointer as u8) {
+    // Parameter numbers are selected by all messages, also the ones not shown
+    let parameter = if args.nrpn
+        && args.template.is_none()
+        && matches!(args.format, DisplayFormat::Default)
+        && status == Status::ControlChange as u8
+        && message.len() >= 3
+    {
+        args.parameters[(message[0] & 0x0F) as usize].process(message[1], message[2])
+    } else {
+        None
+    };
+
+    if args.stats_only || args.paused.load(Ordering::SeqCst) {
         return;
     }
 
-    if args.ignore.song_select && (status == Status::SongSelect as u8) {
+    if classify(message, &args.ignore, &args.filter) != Action::Show {
         return;
     }
 
-    if args.ignore.tune_request && (status == Status::TuneRequest as u8) {
-        return;
+    for throttle in args.throttles.iter_mut() {
+        if throttle.types.matches(status) {
+            if throttle
+                .last_timestamp
+                .is_some_and(|last| timestamp.saturating_sub(last) < throttle.interval)
+            {
+                return;
+            }
+            throttle.last_timestamp = Some(timestamp);
+        }
     }
 
-    if args.ignore.clock && (status == Status::TimingClock as u8) {
-        return;
+    // Realtime messages left out of the count are shown with the number of the previous message,
+    // parameter selections are not shown at all
+    let select = matches!(parameter, Some(ParameterEvent::Select));
+    let counted = !(select || (args.omit_realtime && is_realtime(message)));
+    let message_no = if counted {
+        args.message_count.fetch_add(1, Ordering::SeqCst) + 1
+    } else {
+        args.message_count.load(Ordering::SeqCst)
+    };
+    if let Some(count) = args.count.filter(|_| counted) {
+        if message_no > count {
+            return;
+        }
+        if message_no == count {
+            args.running.store(false, Ordering::SeqCst);
+        }
     }
 
-    if args.ignore.start && (status == Status::Start as u8) {
-        return;
+    let sequence_no = match args.numbering {
+        Some(Numbering::Shown) => Some(message_no),
+        Some(Numbering::All) => Some(received_no),
+        None => None,
+    };
+
+    if !args.thru_all {
+        send_thru(args, message);
     }
 
-    if args.ignore.continue_ && (status == Status::Continue as u8) {
-        return;
+    if let Some(smf) = &args.smf {
+        smf.lock().unwrap().add(timestamp, message);
     }
 
-    if args.ignore.stop && (status == Status::Stop as u8) {
+    if let Some(tui) = &args.tui {
+        tui.send(message.to_vec()).ok();
         return;
     }
 
-    if args.ignore.sensing && (status == Status::ActiveSensing as u8) {
+    if let Some(keyboard) = &args.keyboard {
+        let mut keyboard = keyboard.lock().unwrap();
+        if keyboard
+            .held
+            .process(&MidiMessage::from_array(message), timestamp)
+        {
+            let notes = keyboard.held.notes();
+            keyboard.octave = keyboard_octave(&notes, keyboard.octave);
+            print!(
+                "\r{}\x1b[K",
+                display_keyboard(&notes, keyboard.octave, &args.options.note_naming)
+            );
+            std::io::stdout().flush().ok();
+        }
         return;
     }
 
-    if args.ignore.reset && (status == Status::SystemReset as u8) {
+    let display_timestamp = match &args.time_origin {
+        Some(time_origin) => {
+            let origin = *time_origin.lock().unwrap().get_or_insert(timestamp);
+            timestamp.saturating_sub(origin)
+        }
+        None => timestamp,
+    };
+
+    // Timestamp shown in the human-readable formats
+    let shown_timestamp = match args.options.timestamp_format {
+        TimestampFormat::Wallclock => wallclock_timestamp(),
+        TimestampFormat::Delta => {
+            let previous = args.previous_timestamp.lock().unwrap().replace(timestamp);
+            timestamp.saturating_sub(previous.unwrap_or(timestamp))
+        }
+        _ => display_timestamp,
+    };
+
+    if let Some(template) = &args.template {
+        let line = display_template(
+            template,
+            &args.port_label,
+            shown_timestamp,
+            message,
+            &args.options,
+        );
+        show_line(args, message, sequence_no, &line);
         return;
     }
 
-    if let Some(channel) = args.filter.channel {
-        if (message[0] <= Status::SystemExclusive as u8) && (message[0] & 0x0F != channel - 1) {
+    match parameter {
+        Some(ParameterEvent::Select) => return,
+        Some(ParameterEvent::Value(kind, number, value)) => {
+            let line = display_parameter(
+                &args.port_label,
+                shown_timestamp,
+                message[0] & 0x0F,
+                kind,
+                number,
+                value,
+                &args.options,
+            );
+            show_line(args, message, sequence_no, &line);
+            return;
+        }
+        None => (),
+    }
+
+    if args.cc14
+        && matches!(args.format, DisplayFormat::Default)
+        && status == Status::ControlChange as u8
+        && message.len() >= 3
+    {
+        let channel = message[0] & 0x0F;
+        if let Some((controller, value)) =
+            args.cc14_state[channel as usize].process(message[1], message[2], timestamp)
+        {
+            let line = display_cc14(
+                &args.port_label,
+                shown_timestamp,
+                channel,
+                controller,
+                value,
+                &args.options,
+            );
+            show_line(args, message, sequence_no, &line);
             return;
         }
     }
 
-    match args.format {
-        DisplayFormat::Default => display_default(args.port_id, timestamp, message),
-        DisplayFormat::Raw => display_raw(args.port_id, timestamp, message),
+    let valid = MidiMessage::from_array(message).is_valid();
+
+    let mut line = match args.format {
+        DisplayFormat::Default if !valid => {
+            display_malformed(&args.port_label, shown_timestamp, message, &args.options)
+        }
+        DisplayFormat::Default => {
+            display_default(&args.port_label, shown_timestamp, message, &args.options)
+        }
+        DisplayFormat::Raw => {
+            display_raw(&args.port_label, shown_timestamp, message, &args.options)
+        }
+        DisplayFormat::Min if args.annotate => display_min(message) + &min_annotation(message),
         DisplayFormat::Min => display_min(message),
-        DisplayFormat::MinHex => display_min_hex(message),
+        DisplayFormat::MinHex if args.annotate => {
+            display_min_hex(message, &args.hex_format) + &min_annotation(message)
+        }
+        DisplayFormat::MinHex => display_min_hex(message, &args.hex_format),
+        DisplayFormat::Json => {
+            display_json(args.port_id, display_timestamp, message, &args.options)
+        }
+        DisplayFormat::Csv => display_csv(args.port_id, display_timestamp, message, &args.options),
+        DisplayFormat::Hexdump => display_hexdump(message),
+    };
+
+    if let (DisplayFormat::Default, Some(tempo)) = (args.format, tempo) {
+        line.push_str(&format!("  BPM: {:.1}", tempo));
+    }
+
+    if let (DisplayFormat::Default, Some(duration)) = (args.format, duration) {
+        line.push_str(&match duration {
+            NoteDuration::Held(duration) => {
+                format!("  Duration: {:.1} ms", duration as f64 / 1000.0)
+            }
+            NoteDuration::Orphaned => String::from("  Duration: no note on"),
+        });
     }
-}
 
-/// Display message in default format
-fn display_default(port_id: usize, timestamp: u64, message: &[u8]) {
-    let msg = MidiMessage::from_array(message);
+    if args.options.gm
+        && matches!(args.format, DisplayFormat::Default)
+        && status == Status::ProgramChange as u8
+        && valid
+    {
+        if let Some((msb, lsb)) = args.banks[(message[0] & 0x0F) as usize].bank() {
+            line.push_str(&format!("  Bank: {}/{}", msb, lsb));
+        }
+    }
 
-    let status_text = format!("{}", msg.status());
+    if matches!(args.format, DisplayFormat::Default) && valid {
+        if let Some((field, value)) = MidiMessage::from_array(message).mtc_piece() {
+            if let Some((hours, minutes, seconds, frames, rate)) = args.mtc.piece(field, value) {
+                line.push_str(&format!(
+                    "    Time: {:02}:{:02}:{:02}:{:02} @ {}",
+                    hours, minutes, seconds, frames, rate
+                ));
+            }
+        }
+    }
 
-    let data_text = match msg.status() {
-        Status::NoteOff | Status::NoteOn => format!(
-            "Ch:{:>2}  Note:{:>3}  Vel:{:>3}    {}",
-            msg.channel().unwrap() + 1,
-            msg.data(1),
-            msg.data(2),
-            msg.note_name().unwrap()
-        ),
-        Status::PolyKeyPressure => format!(
-            "Ch:{:>2}  Note:{:>3}  Val:{:>3}    {}",
-            msg.channel().unwrap() + 1,
-            msg.data(1),
-            msg.data(2),
-            msg.note_name().unwrap()
-        ),
-        Status::ControlChange => format!(
-            "Ch:{:>2}  No:  {:>3}  Val:{:>3}    {}",
-            msg.channel().unwrap() + 1,
-            msg.data(1),
-            msg.data(2),
-            msg.cc_name().unwrap()
-        ),
-        Status::ProgramChange | Status::ChannelPressure => format!(
-            "Ch:{:>2}  Val:{:>3}",
-            msg.channel().unwrap() + 1,
-            msg.data(1),
-        ),
-        Status::PitchBend => format!(
-            "Ch:{:>2}  Val:{:>5}",
-            msg.channel().unwrap() + 1,
-            msg.data_as_u16() as i16 - 0x2000,
-        ),
-        Status::MtcQuarterFrame | Status::SongSelect => format!("{:>3}", msg.data(1)),
-        Status::SongPositionPointer => format!("{:>3}  {:>3}", msg.data(1), msg.data(2)),
-        Status::TuneRequest
-        | Status::TimingClock
-        | Status::Start
-        | Status::Continue
-        | Status::Stop
-        | Status::ActiveSensing
-        | Status::SystemReset => String::new(),
-        _ => format!("{:?}", msg.data),
+    show_line(args, message, sequence_no, &line);
+}
+
+/// Write a complete SysEx message to a numbered .syx file if enabled
+/// Incomplete data flushed by the reassembly is not written
+fn write_syx(args: &ReceiveArgs, message: &[u8]) {
+    let Some(dir) = &args.syx_dir else {
+        return;
     };
 
-    println!(
-        "  ({})  {:10.6}  {:21}  {}",
-        port_id,
-        timestamp as f64 / 1e6,
-        status_text,
-        data_text
-    );
+    if message.last() != Some(&(Status::EndOfExclusive as u8)) {
+        return;
+    }
+
+    let number = args.syx_count.fetch_add(1, Ordering::SeqCst) + 1;
+    let path = dir.join(format!("sysex-{:04}.syx", number));
+
+    std::fs::write(&path, message).unwrap_or_else(|err| {
+        eprintln!("Error writing {}: {}", path.display(), err);
+    });
 }
 
-/// Display message in raw format
-fn display_raw(port_id: usize, timestamp: u64, message: &[u8]) {
-    println!(
-        "  ({})  {:10.6}   {:?}",
-        port_id,
-        timestamp as f64 / 1e6,
-        message
-    );
+/// Forward a message to the thru port if enabled
+fn send_thru(args: &ReceiveArgs, message: &[u8]) {
+    if let Some(thru) = &args.thru {
+        thru.lock()
+            .unwrap()
+            .send(message)
+            .unwrap_or_else(|err| eprintln!("Error forwarding message: {}", err));
+    }
 }
 
-/// Display message in min format
-fn display_min(message: &[u8]) {
-    let mut msg = Vec::<String>::new();
+/// Show the display line of a message, prefixed with its sequence number if enabled
+/// If enabled, repeats of the same message are counted instead and the line is held back
+/// until a different message is shown
+fn show_line(args: &ReceiveArgs, message: &[u8], sequence_no: Option<usize>, line: &str) {
+    let line = match sequence_no {
+        Some(number) => format!("{:>7}  {}", number, line),
+        None => String::from(line),
+    };
+
+    let Some(dedup) = &args.dedup else {
+        write_output(&args.output, args.port_id, &line);
+        return;
+    };
 
-    for byte in message {
-        msg.push(format!("{}", byte));
-    }
+    let mut pending = dedup.lock().unwrap();
 
-    println!("{}", msg.join(", "));
-}
+    if let Some(repeat) = pending
+        .as_mut()
+        .filter(|repeat| repeat.port_id == args.port_id && repeat.message == message)
+    {
+        repeat.count += 1;
+        repeat.last_time = Instant::now();
+        return;
+    }
 
-/// Display message in min hex format
-fn display_min_hex(message: &[u8]) {
-    let mut msg = Vec::<String>::new();
+    let previous = pending.replace(PendingRepeat {
+        port_id: args.port_id,
+        message: message.to_vec(),
+        line,
+        count: 1,
+        last_time: Instant::now(),
+    });
 
-    for byte in message {
-        msg.push(format!("0x{:02X}", byte));
+    if let Some(repeat) = previous {
+        write_output(&args.output, repeat.port_id, &repeat.line());
     }
+}
 
-    println!("{}", msg.join(", "));
+/// Write a display line of a port to the terminal and the output files if enabled
+fn write_output(output: &OutputWriter, port_id: usize, line: &str) {
+    output.lock().unwrap().write_line(port_id, line);
 }
@@ -14,8 +14,12 @@ pub struct MidiMessage {
 
 impl MidiMessage {
     /// Return message status enum
+    #[must_use]
     pub fn status(&self) -> Status {
-        let status_byte = self.data[0];
+        let status_byte = match self.data.first() {
+            Some(status_byte) => *status_byte,
+            None => return Status::Error,
+        };
         let status = if status_byte >= 0xF0 {
             status_byte
         } else {
@@ -29,8 +33,9 @@ impl MidiMessage {
     }
 
     /// Return message channel (0-based) or None for system messages
+    #[must_use]
     pub fn channel(&self) -> Option<u8> {
-        let status_byte = self.data[0];
+        let status_byte = *self.data.first()?;
         if status_byte >= 0xF0 {
             None
         } else {
@@ -38,17 +43,118 @@ impl MidiMessage {
         }
     }
 
+    /// Return if message is a note on with velocity 0, which is equivalent to a note off
+    #[must_use]
+    pub fn is_implicit_note_off(&self) -> bool {
+        matches!(self.status(), Status::NoteOn) && self.data.get(2) == Some(&0)
+    }
+
+    /// Return expected message length in bytes derived from the status
+    /// None is returned for system exclusive and unknown messages
+    #[must_use]
+    pub fn expected_len(&self) -> Option<usize> {
+        match self.status() {
+            Status::NoteOff
+            | Status::NoteOn
+            | Status::PolyKeyPressure
+            | Status::ControlChange
+            | Status::PitchBend
+            | Status::SongPositionPointer => Some(3),
+            Status::ProgramChange
+            | Status::ChannelPressure
+            | Status::MtcQuarterFrame
+            | Status::SongSelect => Some(2),
+            Status::TuneRequest
+            | Status::EndOfExclusive
+            | Status::TimingClock
+            | Status::Start
+            | Status::Continue
+            | Status::Stop
+            | Status::ActiveSensing
+            | Status::SystemReset => Some(1),
+            Status::SystemExclusive | Status::Error => None,
+        }
+    }
+
+    /// Return if message length and data bytes match the status
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        let data_valid = |data: &[u8]| data.iter().all(|byte| *byte < 0x80);
+
+        match self.status() {
+            Status::SystemExclusive => {
+                self.data.len() >= 2
+                    && self.data.last() == Some(&(Status::EndOfExclusive as u8))
+                    && data_valid(&self.data[1..self.data.len() - 1])
+            }
+            Status::Error => false,
+            _ => self.expected_len() == Some(self.data.len()) && data_valid(&self.data[1..]),
+        }
+    }
+
     /// Return message data byte
+    #[must_use]
     pub fn data(&self, index: usize) -> u8 {
         self.data[index]
     }
 
+    /// Return message data byte or None if the message is too short
+    #[must_use]
+    pub fn get_data(&self, index: usize) -> Option<u8> {
+        self.data.get(index).copied()
+    }
+
     /// Return message data value as 14-bit value
+    /// As specified for pitch bend and song position pointer, the first data byte holds the
+    /// lower 7 bits (LSB first)
+    #[must_use]
     pub fn data_as_u16(&self) -> u16 {
+        self.data_as_u16_le()
+    }
+
+    /// Return message data value as 14-bit value with explicit little-endian byte order
+    /// The first data byte holds the lower 7 bits, which is the order used by `data_as_u16()`.
+    /// See `get_data_as_u16_msb_first()` for the reverse order.
+    #[must_use]
+    pub fn data_as_u16_le(&self) -> u16 {
         self.data[1] as u16 | ((self.data[2] as u16) << 7)
     }
 
+    /// Return message data value as 14-bit value or None if the message is too short
+    /// The first data byte holds the lower 7 bits (LSB first)
+    #[must_use]
+    pub fn get_data_as_u16(&self) -> Option<u16> {
+        Some(self.get_data(1)? as u16 | ((self.get_data(2)? as u16) << 7))
+    }
+
+    /// Return message data value as 14-bit value with the bytes in reverse order, the first
+    /// data byte holding the upper 7 bits (MSB first), or None if the message is too short
+    /// This is not according to the specification, but used by some implementations.
+    #[must_use]
+    pub fn get_data_as_u16_msb_first(&self) -> Option<u16> {
+        Some(((self.get_data(1)? as u16) << 7) | self.get_data(2)? as u16)
+    }
+
+    /// Return the position of a song position pointer message in sixteenth notes
+    /// The position is given in MIDI beats of 6 timing clocks, which are sixteenth notes.
+    #[must_use]
+    pub fn song_position_sixteenths(&self) -> Option<u16> {
+        match self.status() {
+            Status::SongPositionPointer => self.get_data_as_u16(),
+            _ => None,
+        }
+    }
+
+    /// Create a channel message from status, channel (0-15) and data bytes
+    #[must_use]
+    pub fn from_channel_status(status: Status, channel: u8, data: &[u8]) -> MidiMessage {
+        let mut message = vec![status as u8 | (channel & CHANNEL_MASK)];
+        message.extend_from_slice(data);
+        MidiMessage { data: message }
+    }
+
     /// Create message from array
+    #[must_use]
     pub fn from_array(data: &[u8]) -> MidiMessage {
         MidiMessage {
             data: Vec::from(data),
@@ -56,32 +162,93 @@ impl MidiMessage {
     }
 
     /// Create message from vector
+    #[must_use]
     pub fn from_vec(data: Vec<u8>) -> MidiMessage {
         MidiMessage { data }
     }
 
     /// Return note name for note off, on and poly key pressure messages
     /// Note no 60 is referred as C3
+    #[must_use]
     pub fn note_name(&self) -> Option<String> {
+        self.note_name_with(&NoteNaming::default())
+    }
+
+    /// Return note name for note off, on and poly key pressure messages
+    /// using the given naming convention
+    #[must_use]
+    pub fn note_name_with(&self, naming: &NoteNaming) -> Option<String> {
         match self.status() {
             Status::NoteOff | Status::NoteOn | Status::PolyKeyPressure => {
-                let octave = self.data(1) as i32 / 12 - 2;
-                let key = (self.data(1) % 12) as usize;
-                let names = [
-                    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
-                ];
+                let note = self.get_data(1)?;
+                let octave = note as i32 / 12 + naming.middle_c.octave_offset();
+                let key = (note % 12) as usize;
+                let names = match (naming.style, naming.flats) {
+                    (NoteStyle::Solfege, false) => [
+                        "Do", "Do#", "Re", "Re#", "Mi", "Fa", "Fa#", "Sol", "Sol#", "La", "La#",
+                        "Si",
+                    ],
+                    (NoteStyle::Solfege, true) => [
+                        "Do", "Reb", "Re", "Mib", "Mi", "Fa", "Solb", "Sol", "Lab", "La", "Sib",
+                        "Si",
+                    ],
+                    (_, false) => [
+                        "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+                    ],
+                    (_, true) => [
+                        "C", "Db", "D", "Eb", "E", "F", "Gb", "G", "Ab", "A", "Bb", "B",
+                    ],
+                };
+                if let NoteStyle::Helmholtz = naming.style {
+                    return Some(helmholtz_name(names[key], note as i32 / 12 - 1));
+                }
                 Some(format!("{}{}", String::from(names[key]), octave))
             }
             _ => None,
         }
     }
 
+    /// Return fundamental frequency in Hz for note off, on and poly key pressure messages
+    /// using the standard tuning of A4 (note no 69) to 440 Hz
+    #[must_use]
+    pub fn note_frequency(&self) -> Option<f64> {
+        self.note_frequency_with(440.0)
+    }
+
+    /// Return fundamental frequency in Hz for note off, on and poly key pressure messages
+    /// using the given frequency of A4 (note no 69)
+    #[must_use]
+    pub fn note_frequency_with(&self, tuning: f64) -> Option<f64> {
+        match self.status() {
+            Status::NoteOff | Status::NoteOn | Status::PolyKeyPressure => {
+                let note = self.get_data(1)? as f64;
+                Some(tuning * 2f64.powf((note - 69.0) / 12.0))
+            }
+            _ => None,
+        }
+    }
+
+    /// Return General MIDI instrument name for program change messages
+    #[must_use]
+    pub fn program_name(&self) -> Option<String> {
+        match self.status() {
+            Status::ProgramChange => {
+                let program = self.get_data(1)?;
+                GM_PROGRAMS
+                    .get(program as usize)
+                    .map(|name| String::from(*name))
+            }
+            _ => None,
+        }
+    }
+
     /// Return name for most common control change messages
     /// An empty string is returned for other controller numbers
+    #[must_use]
     pub fn cc_name(&self) -> Option<String> {
         match self.status() {
             Status::ControlChange => {
-                let name = match self.data(1) {
+                let name = match self.get_data(1)? {
                     0 => "Bank Select MSB",
                     1 => "Mod Wheel",
                     2 => "Breath Control",
@@ -116,9 +283,601 @@ impl MidiMessage {
             _ => None,
         }
     }
+
+    /// Return manufacturer name for system exclusive messages
+    /// IDs not contained in the table are returned as hex values
+    #[must_use]
+    pub fn manufacturer_name(&self) -> Option<String> {
+        match self.status() {
+            Status::SystemExclusive => {
+                let id = match self.data.get(1) {
+                    Some(0x00) => match (self.data.get(2), self.data.get(3)) {
+                        (Some(&id1), Some(&id2)) => 0x10000 | ((id1 as u32) << 8) | id2 as u32,
+                        _ => return None,
+                    },
+                    Some(&id) if id < 0x80 => id as u32,
+                    _ => return None,
+                };
+                let name = match id {
+                    0x01 => "Sequential",
+                    0x04 => "Moog",
+                    0x06 => "Lexicon",
+                    0x07 => "Kurzweil",
+                    0x0F => "Ensoniq",
+                    0x10 => "Oberheim",
+                    0x11 => "Apple",
+                    0x18 => "E-mu",
+                    0x33 => "Clavia",
+                    0x3E => "Waldorf",
+                    0x40 => "Kawai",
+                    0x41 => "Roland",
+                    0x42 => "Korg",
+                    0x43 => "Yamaha",
+                    0x44 => "Casio",
+                    0x47 => "Akai",
+                    0x4C => "Sony",
+                    0x52 => "Zoom",
+                    0x7D => "Non-Commercial",
+                    0x7E => "Universal Non-Real Time",
+                    0x7F => "Universal Real Time",
+                    0x1000E => "Alesis",
+                    0x12029 => "Novation",
+                    0x12032 => "Behringer",
+                    0x12033 => "Access",
+                    0x1203C => "Elektron",
+                    0x1206B => "Arturia",
+                    0x12076 => "Teenage Engineering",
+                    0x12109 => "Native Instruments",
+                    _ => {
+                        return Some(if id > 0xFF {
+                            format!("ID 00 {:02X} {:02X}", (id >> 8) & 0x7F, id & 0x7F)
+                        } else {
+                            format!("ID {:02X}", id)
+                        })
+                    }
+                };
+                Some(String::from(name))
+            }
+            _ => None,
+        }
+    }
+
+    /// Return device id and decoded content of recognized universal SysEx messages
+    #[must_use]
+    pub fn universal_sysex(&self) -> Option<(u8, UniversalSysEx)> {
+        if !matches!(self.status(), Status::SystemExclusive) {
+            return None;
+        }
+
+        let data = &self.data;
+        let device = *data.get(2)?;
+        let (sub_id1, sub_id2) = (*data.get(3)?, data.get(4).copied());
+
+        let content = match (data.get(1)?, sub_id1, sub_id2) {
+            (0x7E, 0x06, Some(0x01)) => UniversalSysEx::IdentityRequest,
+            (0x7E, 0x06, Some(0x02)) => {
+                let id_len = if *data.get(5)? == 0x00 { 3 } else { 1 };
+                let body = data.get(5 + id_len..5 + id_len + 8)?;
+                let mut id = vec![Status::SystemExclusive as u8];
+                id.extend_from_slice(&data[5..5 + id_len]);
+                UniversalSysEx::IdentityReply {
+                    manufacturer: MidiMessage::from_vec(id).manufacturer_name()?,
+                    family: body[0] as u16 | (body[1] as u16) << 7,
+                    model: body[2] as u16 | (body[3] as u16) << 7,
+                    version: [body[4], body[5], body[6], body[7]],
+                }
+            }
+            (0x7E, 0x01, _) => UniversalSysEx::SampleDumpHeader {
+                sample: *data.get(4)? as u16 | (*data.get(5)? as u16) << 7,
+            },
+            (0x7E, 0x02, Some(packet)) => UniversalSysEx::SampleDumpPacket { packet },
+            (0x7E, 0x03, _) => UniversalSysEx::SampleDumpRequest {
+                sample: *data.get(4)? as u16 | (*data.get(5)? as u16) << 7,
+            },
+            (0x7E, 0x7C..=0x7F, Some(packet)) => UniversalSysEx::SampleDumpHandshake {
+                reply: match sub_id1 {
+                    0x7C => "Wait",
+                    0x7D => "Cancel",
+                    0x7E => "NAK",
+                    _ => "ACK",
+                },
+                packet,
+            },
+            (0x7F, 0x06, Some(command)) => UniversalSysEx::MachineControl(match command {
+                0x01 => MmcCommand::Stop,
+                0x02 => MmcCommand::Play,
+                0x03 => MmcCommand::DeferredPlay,
+                0x04 => MmcCommand::FastForward,
+                0x05 => MmcCommand::Rewind,
+                0x06 => MmcCommand::RecordStrobe,
+                0x07 => MmcCommand::RecordExit,
+                0x09 => MmcCommand::Pause,
+                0x44 => {
+                    let time = data.get(7..11)?;
+                    MmcCommand::Locate {
+                        hours: time[0] & 0x1F,
+                        minutes: time[1],
+                        seconds: time[2],
+                        frames: time[3],
+                    }
+                }
+                command => MmcCommand::Other(command),
+            }),
+            _ => return None,
+        };
+
+        Some((device, content))
+    }
+
+    /// Return field and value for MTC quarter frame messages
+    #[must_use]
+    pub fn mtc_piece(&self) -> Option<(MtcField, u8)> {
+        match self.status() {
+            Status::MtcQuarterFrame => {
+                let data = self.get_data(1)?;
+                let field = match (data >> 4) & 0x07 {
+                    0 => MtcField::FramesLow,
+                    1 => MtcField::FramesHigh,
+                    2 => MtcField::SecondsLow,
+                    3 => MtcField::SecondsHigh,
+                    4 => MtcField::MinutesLow,
+                    5 => MtcField::MinutesHigh,
+                    6 => MtcField::HoursLow,
+                    _ => MtcField::HoursHighAndRate,
+                };
+                Some((field, data & 0x0F))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Timecode field transmitted by an MTC quarter frame message
+#[derive(Clone, Copy, PartialEq)]
+pub enum MtcField {
+    FramesLow = 0,
+    FramesHigh = 1,
+    SecondsLow = 2,
+    SecondsHigh = 3,
+    MinutesLow = 4,
+    MinutesHigh = 5,
+    HoursLow = 6,
+    HoursHighAndRate = 7,
+}
+
+impl std::fmt::Display for MtcField {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.pad(match *self {
+            MtcField::FramesLow => "frames-low",
+            MtcField::FramesHigh => "frames-high",
+            MtcField::SecondsLow => "seconds-low",
+            MtcField::SecondsHigh => "seconds-high",
+            MtcField::MinutesLow => "minutes-low",
+            MtcField::MinutesHigh => "minutes-high",
+            MtcField::HoursLow => "hours-low",
+            MtcField::HoursHighAndRate => "hours-high/rate",
+        })
+    }
+}
+
+/// Recognized universal SysEx messages
+#[derive(Clone, Debug, PartialEq)]
+pub enum UniversalSysEx {
+    /// Device inquiry request
+    IdentityRequest,
+    /// Device inquiry reply with device family and model codes
+    IdentityReply {
+        manufacturer: String,
+        family: u16,
+        model: u16,
+        version: [u8; 4],
+    },
+    /// Sample dump header
+    SampleDumpHeader { sample: u16 },
+    /// Sample dump data packet
+    SampleDumpPacket { packet: u8 },
+    /// Sample dump request
+    SampleDumpRequest { sample: u16 },
+    /// Sample dump handshake like ACK or NAK
+    SampleDumpHandshake { reply: &'static str, packet: u8 },
+    /// MIDI Machine Control command
+    MachineControl(MmcCommand),
+}
+
+impl std::fmt::Display for UniversalSysEx {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            UniversalSysEx::IdentityRequest => write!(f, "Universal NRT  Device Inquiry"),
+            UniversalSysEx::IdentityReply {
+                manufacturer,
+                family,
+                model,
+                version,
+            } => write!(
+                f,
+                "Universal NRT  Device Inquiry Reply  {}  family={} model={} version={}.{}.{}.{}",
+                manufacturer, family, model, version[0], version[1], version[2], version[3]
+            ),
+            UniversalSysEx::SampleDumpHeader { sample } => {
+                write!(f, "Universal NRT  Sample Dump Header  sample={}", sample)
+            }
+            UniversalSysEx::SampleDumpPacket { packet } => {
+                write!(f, "Universal NRT  Sample Dump Packet  packet={}", packet)
+            }
+            UniversalSysEx::SampleDumpRequest { sample } => {
+                write!(f, "Universal NRT  Sample Dump Request  sample={}", sample)
+            }
+            UniversalSysEx::SampleDumpHandshake { reply, packet } => {
+                write!(f, "Universal NRT  Sample Dump {}  packet={}", reply, packet)
+            }
+            UniversalSysEx::MachineControl(command) => {
+                write!(f, "Universal RT  MMC {}", command)
+            }
+        }
+    }
+}
+
+/// MIDI Machine Control commands
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MmcCommand {
+    Stop,
+    Play,
+    DeferredPlay,
+    FastForward,
+    Rewind,
+    RecordStrobe,
+    RecordExit,
+    Pause,
+    Locate {
+        hours: u8,
+        minutes: u8,
+        seconds: u8,
+        frames: u8,
+    },
+    Other(u8),
+}
+
+impl std::fmt::Display for MmcCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MmcCommand::Stop => write!(f, "Stop"),
+            MmcCommand::Play => write!(f, "Play"),
+            MmcCommand::DeferredPlay => write!(f, "Deferred Play"),
+            MmcCommand::FastForward => write!(f, "Fast Forward"),
+            MmcCommand::Rewind => write!(f, "Rewind"),
+            MmcCommand::RecordStrobe => write!(f, "Record Strobe"),
+            MmcCommand::RecordExit => write!(f, "Record Exit"),
+            MmcCommand::Pause => write!(f, "Pause"),
+            MmcCommand::Locate {
+                hours,
+                minutes,
+                seconds,
+                frames,
+            } => write!(
+                f,
+                "Locate {:02}:{:02}:{:02}:{:02}",
+                hours, minutes, seconds, frames
+            ),
+            MmcCommand::Other(command) => write!(f, "Command {:02X}", command),
+        }
+    }
+}
+
+/// Return dynamic marking for a note velocity
+/// Velocity 0 is returned as note off, because a note on with velocity 0 is equivalent
+#[must_use]
+pub fn velocity_dynamic(velocity: u8) -> &'static str {
+    match velocity {
+        0 => "note off",
+        1..=15 => "ppp",
+        16..=31 => "pp",
+        32..=47 => "p",
+        48..=63 => "mp",
+        64..=79 => "mf",
+        80..=95 => "f",
+        96..=111 => "ff",
+        _ => "fff",
+    }
+}
+
+/// General MIDI instrument names by program number
+static GM_PROGRAMS: [&str; 128] = [
+    // Piano
+    "Acoustic Grand Piano",
+    "Bright Acoustic Piano",
+    "Electric Grand Piano",
+    "Honky-tonk Piano",
+    "Electric Piano 1",
+    "Electric Piano 2",
+    "Harpsichord",
+    "Clavinet",
+    // Chromatic Percussion
+    "Celesta",
+    "Glockenspiel",
+    "Music Box",
+    "Vibraphone",
+    "Marimba",
+    "Xylophone",
+    "Tubular Bells",
+    "Dulcimer",
+    // Organ
+    "Drawbar Organ",
+    "Percussive Organ",
+    "Rock Organ",
+    "Church Organ",
+    "Reed Organ",
+    "Accordion",
+    "Harmonica",
+    "Tango Accordion",
+    // Guitar
+    "Acoustic Guitar (nylon)",
+    "Acoustic Guitar (steel)",
+    "Electric Guitar (jazz)",
+    "Electric Guitar (clean)",
+    "Electric Guitar (muted)",
+    "Overdriven Guitar",
+    "Distortion Guitar",
+    "Guitar Harmonics",
+    // Bass
+    "Acoustic Bass",
+    "Electric Bass (finger)",
+    "Electric Bass (pick)",
+    "Fretless Bass",
+    "Slap Bass 1",
+    "Slap Bass 2",
+    "Synth Bass 1",
+    "Synth Bass 2",
+    // Strings
+    "Violin",
+    "Viola",
+    "Cello",
+    "Contrabass",
+    "Tremolo Strings",
+    "Pizzicato Strings",
+    "Orchestral Harp",
+    "Timpani",
+    // Ensemble
+    "String Ensemble 1",
+    "String Ensemble 2",
+    "Synth Strings 1",
+    "Synth Strings 2",
+    "Choir Aahs",
+    "Voice Oohs",
+    "Synth Voice",
+    "Orchestra Hit",
+    // Brass
+    "Trumpet",
+    "Trombone",
+    "Tuba",
+    "Muted Trumpet",
+    "French Horn",
+    "Brass Section",
+    "Synth Brass 1",
+    "Synth Brass 2",
+    // Reed
+    "Soprano Sax",
+    "Alto Sax",
+    "Tenor Sax",
+    "Baritone Sax",
+    "Oboe",
+    "English Horn",
+    "Bassoon",
+    "Clarinet",
+    // Pipe
+    "Piccolo",
+    "Flute",
+    "Recorder",
+    "Pan Flute",
+    "Blown Bottle",
+    "Shakuhachi",
+    "Whistle",
+    "Ocarina",
+    // Synth Lead
+    "Lead 1 (square)",
+    "Lead 2 (sawtooth)",
+    "Lead 3 (calliope)",
+    "Lead 4 (chiff)",
+    "Lead 5 (charang)",
+    "Lead 6 (voice)",
+    "Lead 7 (fifths)",
+    "Lead 8 (bass + lead)",
+    // Synth Pad
+    "Pad 1 (new age)",
+    "Pad 2 (warm)",
+    "Pad 3 (polysynth)",
+    "Pad 4 (choir)",
+    "Pad 5 (bowed)",
+    "Pad 6 (metallic)",
+    "Pad 7 (halo)",
+    "Pad 8 (sweep)",
+    // Synth Effects
+    "FX 1 (rain)",
+    "FX 2 (soundtrack)",
+    "FX 3 (crystal)",
+    "FX 4 (atmosphere)",
+    "FX 5 (brightness)",
+    "FX 6 (goblins)",
+    "FX 7 (echoes)",
+    "FX 8 (sci-fi)",
+    // Ethnic
+    "Sitar",
+    "Banjo",
+    "Shamisen",
+    "Koto",
+    "Kalimba",
+    "Bagpipe",
+    "Fiddle",
+    "Shanai",
+    // Percussive
+    "Tinkle Bell",
+    "Agogo",
+    "Steel Drums",
+    "Woodblock",
+    "Taiko Drum",
+    "Melodic Tom",
+    "Synth Drum",
+    "Reverse Cymbal",
+    // Sound Effects
+    "Guitar Fret Noise",
+    "Breath Noise",
+    "Seashore",
+    "Bird Tweet",
+    "Telephone Ring",
+    "Helicopter",
+    "Applause",
+    "Gunshot",
+];
+
+/// Return General MIDI percussion instrument name for a note number on the drum channel
+#[must_use]
+pub fn drum_name(note: u8) -> Option<&'static str> {
+    let name = match note {
+        35 => "Acoustic Bass Drum",
+        36 => "Bass Drum 1",
+        37 => "Side Stick",
+        38 => "Acoustic Snare",
+        39 => "Hand Clap",
+        40 => "Electric Snare",
+        41 => "Low Floor Tom",
+        42 => "Closed Hi-Hat",
+        43 => "High Floor Tom",
+        44 => "Pedal Hi-Hat",
+        45 => "Low Tom",
+        46 => "Open Hi-Hat",
+        47 => "Low-Mid Tom",
+        48 => "Hi-Mid Tom",
+        49 => "Crash Cymbal 1",
+        50 => "High Tom",
+        51 => "Ride Cymbal 1",
+        52 => "Chinese Cymbal",
+        53 => "Ride Bell",
+        54 => "Tambourine",
+        55 => "Splash Cymbal",
+        56 => "Cowbell",
+        57 => "Crash Cymbal 2",
+        58 => "Vibraslap",
+        59 => "Ride Cymbal 2",
+        60 => "Hi Bongo",
+        61 => "Low Bongo",
+        62 => "Mute Hi Conga",
+        63 => "Open Hi Conga",
+        64 => "Low Conga",
+        65 => "High Timbale",
+        66 => "Low Timbale",
+        67 => "High Agogo",
+        68 => "Low Agogo",
+        69 => "Cabasa",
+        70 => "Maracas",
+        71 => "Short Whistle",
+        72 => "Long Whistle",
+        73 => "Short Guiro",
+        74 => "Long Guiro",
+        75 => "Claves",
+        76 => "Hi Wood Block",
+        77 => "Low Wood Block",
+        78 => "Mute Cuica",
+        79 => "Open Cuica",
+        80 => "Mute Triangle",
+        81 => "Open Triangle",
+        _ => return None,
+    };
+
+    Some(name)
+}
+
+/// Octave numbering convention for note names
+#[derive(Clone, Copy, Default)]
+pub enum MiddleC {
+    /// Note no 60 is C3, as used by Yamaha and many DAWs
+    #[default]
+    C3,
+    /// Note no 60 is C4, as used by scientific pitch notation and Roland
+    C4,
 }
 
-#[derive(Clone, Copy, TryFromPrimitive)]
+impl MiddleC {
+    /// Return the octave of note no 0
+    fn octave_offset(self) -> i32 {
+        match self {
+            MiddleC::C3 => -2,
+            MiddleC::C4 => -1,
+        }
+    }
+}
+
+/// Spelling of note names
+#[derive(Clone, Copy, Default)]
+pub enum NoteStyle {
+    /// Letter names with octave numbers like C3
+    #[default]
+    Scientific,
+    /// Syllables with octave numbers like Do3
+    Solfege,
+    /// Letter case and primes or commas for octaves like c'
+    Helmholtz,
+}
+
+/// Return a note name in Helmholtz notation for the octave in scientific pitch notation
+/// Middle C (C4) is c', the octave below is lowercase without marks, lower octaves are
+/// uppercase with an increasing number of commas
+fn helmholtz_name(name: &str, octave: i32) -> String {
+    if octave >= 3 {
+        format!(
+            "{}{}",
+            name.to_lowercase(),
+            "'".repeat((octave - 3) as usize)
+        )
+    } else {
+        format!("{}{}", name, ",".repeat((2 - octave) as usize))
+    }
+}
+
+/// Settings for the conversion of note numbers into names
+#[derive(Clone, Copy, Default)]
+pub struct NoteNaming {
+    pub middle_c: MiddleC,
+    /// Use flat instead of sharp spellings for black keys
+    pub flats: bool,
+    /// Spelling of the names
+    pub style: NoteStyle,
+}
+
+/// Time signature for the conversion of song positions into bars and beats
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeSignature {
+    /// Number of beats per bar
+    pub beats: u8,
+    /// Note value of a beat, 1, 2, 4, 8 or 16
+    pub beat_value: u8,
+}
+
+impl Default for TimeSignature {
+    fn default() -> Self {
+        Self {
+            beats: 4,
+            beat_value: 4,
+        }
+    }
+}
+
+impl TimeSignature {
+    /// Return bar, beat and sixteenth within the beat, counted from 1, of a position in
+    /// sixteenth notes
+    #[must_use]
+    pub fn position(&self, sixteenths: u16) -> (u32, u32, u32) {
+        let per_beat = (16 / self.beat_value.max(1) as u32).max(1);
+        let per_bar = per_beat * self.beats.max(1) as u32;
+        let sixteenths = sixteenths as u32;
+
+        (
+            sixteenths / per_bar + 1,
+            sixteenths % per_bar / per_beat + 1,
+            sixteenths % per_beat + 1,
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, TryFromPrimitive)]
 #[repr(u8)]
 pub enum Status {
     // Channel messages
@@ -148,33 +907,420 @@ pub enum Status {
     Error = 0x00,
 }
 
+impl Status {
+    /// All status values
+    pub const ALL: [Status; 20] = [
+        Status::NoteOff,
+        Status::NoteOn,
+        Status::PolyKeyPressure,
+        Status::ControlChange,
+        Status::ProgramChange,
+        Status::ChannelPressure,
+        Status::PitchBend,
+        Status::SystemExclusive,
+        Status::MtcQuarterFrame,
+        Status::SongPositionPointer,
+        Status::SongSelect,
+        Status::TuneRequest,
+        Status::EndOfExclusive,
+        Status::TimingClock,
+        Status::Start,
+        Status::Continue,
+        Status::Stop,
+        Status::ActiveSensing,
+        Status::SystemReset,
+        Status::Error,
+    ];
+
+    /// Return short name as used for command line options
+    /// Note on and off are only available together, so they have no short names
+    #[must_use]
+    pub fn token(self) -> Option<&'static str> {
+        match self {
+            Status::PolyKeyPressure => Some("polyat"),
+            Status::ControlChange => Some("cc"),
+            Status::ProgramChange => Some("pc"),
+            Status::ChannelPressure => Some("at"),
+            Status::PitchBend => Some("pb"),
+            Status::SystemExclusive => Some("sysex"),
+            Status::MtcQuarterFrame => Some("mtc"),
+            Status::SongPositionPointer => Some("spp"),
+            Status::SongSelect => Some("songsel"),
+            Status::TuneRequest => Some("tune"),
+            Status::TimingClock => Some("clock"),
+            Status::Start => Some("start"),
+            Status::Continue => Some("continue"),
+            Status::Stop => Some("stop"),
+            Status::ActiveSensing => Some("sensing"),
+            Status::SystemReset => Some("reset"),
+            Status::EndOfExclusive => Some("eox"),
+            Status::NoteOff | Status::NoteOn | Status::Error => None,
+        }
+    }
+}
+
+/// Parse a status from its short name or its displayed name, ignoring case
+impl std::str::FromStr for Status {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim();
+
+        Status::ALL
+            .iter()
+            .find(|status| {
+                status
+                    .token()
+                    .is_some_and(|token| token.eq_ignore_ascii_case(name))
+                    || status.to_string().eq_ignore_ascii_case(name)
+            })
+            .copied()
+            .ok_or(format!("unknown message type \"{}\"", name))
+    }
+}
+
 impl std::fmt::Display for Status {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match *self {
-                Status::NoteOff => "Note Off",
-                Status::NoteOn => "Note On",
-                Status::PolyKeyPressure => "Poly Key Pressure",
-                Status::ControlChange => "Control Change",
-                Status::ProgramChange => "Program Change",
-                Status::ChannelPressure => "Channel Pressure",
-                Status::PitchBend => "Pitch Bend",
-                Status::SystemExclusive => "System Exclusive",
-                Status::MtcQuarterFrame => "MTC Quarter Frame",
-                Status::SongPositionPointer => "Song Position Pointer",
-                Status::SongSelect => "Song Select",
-                Status::TuneRequest => "Tune Request",
-                Status::EndOfExclusive => "End of Exclusive",
-                Status::TimingClock => "Timing Clock",
-                Status::Start => "Start",
-                Status::Continue => "Continue",
-                Status::Stop => "Stop",
-                Status::ActiveSensing => "Active Sensing",
-                Status::SystemReset => "System Reset",
-                Status::Error => "Error or unknown",
+        f.pad(match *self {
+            Status::NoteOff => "Note Off",
+            Status::NoteOn => "Note On",
+            Status::PolyKeyPressure => "Poly Key Pressure",
+            Status::ControlChange => "Control Change",
+            Status::ProgramChange => "Program Change",
+            Status::ChannelPressure => "Channel Pressure",
+            Status::PitchBend => "Pitch Bend",
+            Status::SystemExclusive => "System Exclusive",
+            Status::MtcQuarterFrame => "MTC Quarter Frame",
+            Status::SongPositionPointer => "Song Position Pointer",
+            Status::SongSelect => "Song Select",
+            Status::TuneRequest => "Tune Request",
+            Status::EndOfExclusive => "End of Exclusive",
+            Status::TimingClock => "Timing Clock",
+            Status::Start => "Start",
+            Status::Continue => "Continue",
+            Status::Stop => "Stop",
+            Status::ActiveSensing => "Active Sensing",
+            Status::SystemReset => "System Reset",
+            Status::Error => "Error or unknown",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_data_short_message() {
+        let msg = MidiMessage::from_array(&[0x90, 60]);
+        assert_eq!(msg.get_data(1), Some(60));
+        assert_eq!(msg.get_data(2), None);
+        assert_eq!(msg.get_data_as_u16(), None);
+    }
+
+    #[test]
+    fn get_data_as_u16_complete_message() {
+        let msg = MidiMessage::from_array(&[0xE0, 0x00, 0x40]);
+        assert_eq!(msg.get_data_as_u16(), Some(0x2000));
+    }
+
+    #[test]
+    fn data_as_u16_byte_order() {
+        // The first data byte is the LSB according to the MIDI specification
+        for (message, value) in [
+            (&[0xE0, 0x01, 0x00], 1),
+            (&[0xE0, 0x00, 0x01], 128),
+            (&[0xE0, 0x7F, 0x7F], 16383),
+            (&[0xF2, 0x10, 0x02], 0x110),
+        ] {
+            let msg = MidiMessage::from_array(message);
+            assert_eq!(msg.data_as_u16(), value);
+            assert_eq!(msg.data_as_u16_le(), value);
+            assert_eq!(msg.get_data_as_u16(), Some(value));
+        }
+
+        let msg = MidiMessage::from_array(&[0xE0, 0x01, 0x00]);
+        assert_eq!(msg.get_data_as_u16_msb_first(), Some(128));
+        let msg = MidiMessage::from_array(&[0xE0, 0x40, 0x00]);
+        assert_eq!(msg.get_data_as_u16_msb_first(), Some(0x2000));
+        let msg = MidiMessage::from_array(&[0xE0, 0x40]);
+        assert_eq!(msg.get_data_as_u16_msb_first(), None);
+    }
+
+    #[test]
+    fn song_position() {
+        let msg = MidiMessage::from_array(&[0xF2, 0x24, 0x00]);
+        assert_eq!(msg.song_position_sixteenths(), Some(36));
+        assert_eq!(TimeSignature::default().position(36), (3, 2, 1));
+        assert_eq!(TimeSignature::default().position(0), (1, 1, 1));
+        assert_eq!(TimeSignature::default().position(15), (1, 4, 4));
+
+        let waltz = TimeSignature {
+            beats: 3,
+            beat_value: 4,
+        };
+        assert_eq!(waltz.position(36), (4, 1, 1));
+
+        let six_eight = TimeSignature {
+            beats: 6,
+            beat_value: 8,
+        };
+        assert_eq!(six_eight.position(13), (2, 1, 2));
+
+        assert_eq!(
+            MidiMessage::from_array(&[0xF2, 0x24]).song_position_sixteenths(),
+            None
+        );
+        assert_eq!(
+            MidiMessage::from_array(&[0xE0, 0x24, 0x00]).song_position_sixteenths(),
+            None
+        );
+    }
+
+    #[test]
+    fn channel_messages() {
+        let msg = MidiMessage::from_channel_status(Status::NoteOn, 9, &[36, 100]);
+        assert_eq!(msg.data, vec![0x99, 36, 100]);
+        assert!(msg.is_valid());
+
+        let msg = MidiMessage::from_channel_status(Status::ProgramChange, 0, &[5]);
+        assert_eq!(msg.data, vec![0xC0, 5]);
+        assert!(msg.is_valid());
+    }
+
+    #[test]
+    fn status_only_messages() {
+        for status in [0x80, 0x90, 0xA0, 0xB0, 0xF1] {
+            let msg = MidiMessage::from_array(&[status]);
+            assert_eq!(msg.note_name(), None);
+            assert_eq!(msg.cc_name(), None);
+            assert!(msg.mtc_piece().is_none());
+            assert!(!msg.is_valid());
+        }
+    }
+
+    fn note_name(note: u8) -> Option<String> {
+        MidiMessage::from_array(&[0x90, note, 100]).note_name()
+    }
+
+    #[test]
+    fn note_name_range() {
+        assert_eq!(note_name(60).as_deref(), Some("C3"));
+        assert_eq!(note_name(0).as_deref(), Some("C-2"));
+        assert_eq!(note_name(127).as_deref(), Some("G8"));
+    }
+
+    #[test]
+    fn note_name_semitones() {
+        let names = [
+            "C3", "C#3", "D3", "D#3", "E3", "F3", "F#3", "G3", "G#3", "A3", "A#3", "B3", "C4",
+        ];
+        for (offset, name) in names.iter().enumerate() {
+            assert_eq!(note_name(60 + offset as u8).as_deref(), Some(*name));
+        }
+    }
+
+    #[test]
+    fn note_name_middle_c() {
+        let naming = NoteNaming {
+            middle_c: MiddleC::C4,
+            ..Default::default()
+        };
+        let msg = MidiMessage::from_array(&[0x90, 60, 100]);
+        assert_eq!(msg.note_name_with(&naming).as_deref(), Some("C4"));
+        let msg = MidiMessage::from_array(&[0x90, 0, 100]);
+        assert_eq!(msg.note_name_with(&naming).as_deref(), Some("C-1"));
+    }
+
+    #[test]
+    fn note_name_accidentals() {
+        let msg = MidiMessage::from_array(&[0x90, 61, 100]);
+        assert_eq!(msg.note_name().as_deref(), Some("C#3"));
+
+        let naming = NoteNaming {
+            flats: true,
+            ..Default::default()
+        };
+        assert_eq!(msg.note_name_with(&naming).as_deref(), Some("Db3"));
+        let msg = MidiMessage::from_array(&[0x90, 70, 100]);
+        assert_eq!(msg.note_name_with(&naming).as_deref(), Some("Bb3"));
+        let msg = MidiMessage::from_array(&[0x90, 72, 100]);
+        assert_eq!(msg.note_name_with(&naming).as_deref(), Some("C4"));
+    }
+
+    #[test]
+    fn note_name_styles() {
+        let name = |note, style, flats| {
+            let naming = NoteNaming {
+                style,
+                flats,
+                ..Default::default()
+            };
+            MidiMessage::from_array(&[0x90, note, 100]).note_name_with(&naming)
+        };
+
+        assert_eq!(
+            name(60, NoteStyle::Scientific, false).as_deref(),
+            Some("C3")
+        );
+        assert_eq!(name(60, NoteStyle::Solfege, false).as_deref(), Some("Do3"));
+        assert_eq!(name(67, NoteStyle::Solfege, false).as_deref(), Some("Sol3"));
+        assert_eq!(name(70, NoteStyle::Solfege, true).as_deref(), Some("Sib3"));
+        assert_eq!(name(71, NoteStyle::Solfege, false).as_deref(), Some("Si3"));
+
+        assert_eq!(name(60, NoteStyle::Helmholtz, false).as_deref(), Some("c'"));
+        assert_eq!(
+            name(73, NoteStyle::Helmholtz, false).as_deref(),
+            Some("c#''")
+        );
+        assert_eq!(name(57, NoteStyle::Helmholtz, false).as_deref(), Some("a"));
+        assert_eq!(name(36, NoteStyle::Helmholtz, false).as_deref(), Some("C"));
+        assert_eq!(name(34, NoteStyle::Helmholtz, true).as_deref(), Some("Bb,"));
+        assert_eq!(
+            name(0, NoteStyle::Helmholtz, false).as_deref(),
+            Some("C,,,")
+        );
+        assert_eq!(
+            name(127, NoteStyle::Helmholtz, false).as_deref(),
+            Some("g''''''")
+        );
+    }
+
+    #[test]
+    fn note_name_statuses() {
+        for status in [0x80, 0x90, 0xA0] {
+            let msg = MidiMessage::from_array(&[status, 60, 0]);
+            assert_eq!(msg.note_name().as_deref(), Some("C3"));
+        }
+        for message in [
+            &[0xB0, 60, 0][..],
+            &[0xC0, 60],
+            &[0xD0, 60],
+            &[0xE0, 60, 0],
+            &[0xF2, 60, 0],
+            &[0xF0, 60, 0xF7],
+        ] {
+            assert_eq!(MidiMessage::from_array(message).note_name(), None);
+        }
+    }
+
+    #[test]
+    fn universal_sysex_device_inquiry() {
+        let msg = MidiMessage::from_array(&[0xF0, 0x7E, 0x7F, 0x06, 0x01, 0xF7]);
+        assert_eq!(
+            msg.universal_sysex(),
+            Some((0x7F, UniversalSysEx::IdentityRequest))
+        );
+
+        let msg = MidiMessage::from_array(&[
+            0xF0, 0x7E, 0x10, 0x06, 0x02, 0x41, 0x2A, 0x02, 0x03, 0x00, 0x01, 0x02, 0x03, 0x04,
+            0xF7,
+        ]);
+        assert_eq!(
+            msg.universal_sysex(),
+            Some((
+                0x10,
+                UniversalSysEx::IdentityReply {
+                    manufacturer: String::from("Roland"),
+                    family: 0x012A,
+                    model: 3,
+                    version: [1, 2, 3, 4],
+                }
+            ))
+        );
+
+        let msg = MidiMessage::from_array(&[0xF0, 0x7E, 0x10, 0x06, 0x02, 0x41, 0xF7]);
+        assert_eq!(msg.universal_sysex(), None);
+    }
+
+    #[test]
+    fn universal_sysex_machine_control() {
+        let msg = MidiMessage::from_array(&[0xF0, 0x7F, 0x7F, 0x06, 0x02, 0xF7]);
+        assert_eq!(
+            msg.universal_sysex(),
+            Some((0x7F, UniversalSysEx::MachineControl(MmcCommand::Play)))
+        );
+
+        let msg = MidiMessage::from_array(&[
+            0xF0, 0x7F, 0x7F, 0x06, 0x44, 0x06, 0x01, 0x21, 0x02, 0x03, 0x04, 0x00, 0xF7,
+        ]);
+        assert_eq!(
+            msg.universal_sysex(),
+            Some((
+                0x7F,
+                UniversalSysEx::MachineControl(MmcCommand::Locate {
+                    hours: 1,
+                    minutes: 2,
+                    seconds: 3,
+                    frames: 4
+                })
+            ))
+        );
+    }
+
+    #[test]
+    fn universal_sysex_other() {
+        let msg = MidiMessage::from_array(&[0xF0, 0x7E, 0x00, 0x7F, 0x05, 0xF7]);
+        assert_eq!(
+            msg.universal_sysex(),
+            Some((
+                0,
+                UniversalSysEx::SampleDumpHandshake {
+                    reply: "ACK",
+                    packet: 5
+                }
+            ))
+        );
+
+        let msg = MidiMessage::from_array(&[0xF0, 0x41, 0x10, 0x06, 0x01, 0xF7]);
+        assert_eq!(msg.universal_sysex(), None);
+    }
+
+    #[test]
+    fn program_names() {
+        let name = |program| MidiMessage::from_array(&[0xC0, program]).program_name();
+        assert_eq!(name(0).as_deref(), Some("Acoustic Grand Piano"));
+        assert_eq!(name(40).as_deref(), Some("Violin"));
+        assert_eq!(name(127).as_deref(), Some("Gunshot"));
+        assert_eq!(name(128), None);
+        assert_eq!(MidiMessage::from_array(&[0xC0]).program_name(), None);
+        assert_eq!(MidiMessage::from_array(&[0xD0, 0]).program_name(), None);
+    }
+
+    #[test]
+    fn status_round_trip() {
+        for status in Status::ALL {
+            assert_eq!(status.to_string().parse::<Status>(), Ok(status));
+            assert_eq!(
+                status.to_string().to_uppercase().parse::<Status>(),
+                Ok(status)
+            );
+            if let Some(token) = status.token() {
+                assert_eq!(token.parse::<Status>(), Ok(status));
             }
-        )
+        }
+
+        for byte in 0..=255 {
+            if let Ok(status) = Status::try_from(byte) {
+                assert!(Status::ALL.contains(&status));
+            }
+        }
+    }
+
+    #[test]
+    fn status_from_str() {
+        assert_eq!("cc".parse::<Status>(), Ok(Status::ControlChange));
+        assert_eq!(" Note On ".parse::<Status>(), Ok(Status::NoteOn));
+        assert_eq!("timing clock".parse::<Status>(), Ok(Status::TimingClock));
+        assert!("note".parse::<Status>().is_err());
+        assert!("".parse::<Status>().is_err());
+    }
+
+    #[test]
+    fn empty_message() {
+        let msg = MidiMessage::from_array(&[]);
+        assert!(matches!(msg.status(), Status::Error));
+        assert_eq!(msg.channel(), None);
+        assert!(!msg.is_valid());
     }
 }
@@ -0,0 +1,272 @@
+//! Monitoring of input ports with a custom callback
+//!
+//! The [`Monitor`] builder connects to input ports via midir and passes each received message
+//! that is not filtered out to a callback instead of the built-in display functions.
+//!
+//! ```no_run
+//! use midimon::messages::Status;
+//! use midimon::monitor::Monitor;
+//!
+//! let connection = Monitor::new()
+//!     .port("keys")
+//!     .ignore(Status::TimingClock)
+//!     .ignore(Status::ActiveSensing)
+//!     .on_message(|message, port_id, timestamp| {
+//!         println!("{} {} {:?}", port_id, timestamp, message.status());
+//!     })
+//!     .start()
+//!     .unwrap();
+//!
+//! std::thread::sleep(std::time::Duration::from_secs(10));
+//! connection.close();
+//! ```
+
+use std::sync::{Arc, Mutex};
+
+use midir::{MidiIO, MidiInput, MidiInputConnection};
+
+use crate::error::MidimonError;
+use crate::messages::{MidiMessage, Status};
+
+/// Callback for received messages with port id and timestamp in microseconds
+type Callback = Box<dyn FnMut(&MidiMessage, usize, u64) + Send>;
+
+/// Builder for monitoring input ports
+pub struct Monitor {
+    client_name: String,
+    ports: Vec<String>,
+    filter: Filter,
+    callback: Option<Callback>,
+}
+
+/// Filter applied to received messages before they are passed to the callback
+#[derive(Clone, Default)]
+struct Filter {
+    ignored: Vec<Status>,
+    /// Bitmask of channels 0-15
+    channels: Option<u16>,
+}
+
+impl Filter {
+    /// Return if a message passes the filter
+    fn accepts(&self, message: &MidiMessage) -> bool {
+        if self.ignored.contains(&message.status()) {
+            return false;
+        }
+
+        match (self.channels, message.channel()) {
+            (Some(channels), Some(channel)) => channels & (1 << channel) != 0,
+            _ => true,
+        }
+    }
+}
+
+impl Default for Monitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Monitor {
+    /// Return a builder that monitors all input ports without filtering
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            client_name: String::from("midimon"),
+            ports: Vec::new(),
+            filter: Filter::default(),
+            callback: None,
+        }
+    }
+
+    /// Set the client name shown to the operating system
+    #[must_use]
+    pub fn client_name(mut self, name: &str) -> Self {
+        self.client_name = String::from(name);
+        self
+    }
+
+    /// Add ports to monitor by id or by a part of their name, ignoring case
+    /// All ports are monitored if none are added
+    #[must_use]
+    pub fn port(mut self, port: &str) -> Self {
+        self.ports.push(String::from(port));
+        self
+    }
+
+    /// Ignore messages with the given status
+    /// Note on and note off are separate statuses and have to be ignored both
+    #[must_use]
+    pub fn ignore(mut self, status: Status) -> Self {
+        self.filter.ignored.push(status);
+        self
+    }
+
+    /// Pass only channel messages of the given channel (0-based), can be called repeatedly
+    /// System messages are not affected
+    #[must_use]
+    pub fn channel(mut self, channel: u8) -> Self {
+        let channels = self.filter.channels.unwrap_or(0);
+        self.filter.channels = Some(channels | 1 << (channel & 0x0F));
+        self
+    }
+
+    /// Set the callback for received messages
+    /// The callback gets the message, the id of the port and the timestamp in microseconds
+    #[must_use]
+    pub fn on_message<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&MidiMessage, usize, u64) + Send + 'static,
+    {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Connect to the ports and start monitoring
+    /// Monitoring continues until the returned connection is closed or dropped
+    pub fn start(self) -> Result<MonitorConnection, MidimonError> {
+        let callback = self.callback.ok_or_else(|| {
+            MidimonError::InvalidArgument(String::from("No callback for received messages set"))
+        })?;
+        let callback = Arc::new(Mutex::new(callback));
+
+        let client = format!("{} input", self.client_name);
+        let midi_in = MidiInput::new(&client)?;
+
+        let port_ids = if self.ports.is_empty() {
+            (0..midi_in.port_count()).collect()
+        } else {
+            let mut port_ids = Vec::new();
+            for port in &self.ports {
+                port_ids.extend(select_ports(&midi_in, port, "input")?);
+            }
+            unique_port_ids(port_ids)
+        };
+
+        let mut connections = Vec::new();
+
+        for port_id in port_ids {
+            let mut midi_in = MidiInput::new(&client)?;
+            midi_in.ignore(midir::Ignore::None);
+            let port = midi_in.ports().get(port_id).cloned().ok_or_else(|| {
+                MidimonError::PortNotFound(format!("No input port with id {}", port_id))
+            })?;
+            let port_name = midi_in.port_name(&port)?;
+            let filter = self.filter.clone();
+            let callback = callback.clone();
+
+            connections.push(midi_in.connect(
+                &port,
+                &port_name,
+                move |timestamp, data, _| {
+                    let message = MidiMessage::from_array(data);
+                    if filter.accepts(&message) {
+                        (callback.lock().unwrap())(&message, port_id, timestamp);
+                    }
+                },
+                (),
+            )?);
+        }
+
+        Ok(MonitorConnection { connections })
+    }
+}
+
+/// Connections to the monitored ports
+pub struct MonitorConnection {
+    connections: Vec<MidiInputConnection<()>>,
+}
+
+impl MonitorConnection {
+    /// Return the number of monitored ports
+    #[must_use]
+    pub fn port_count(&self) -> usize {
+        self.connections.len()
+    }
+
+    /// Stop monitoring and close all connections
+    pub fn close(self) {
+        for connection in self.connections {
+            connection.close();
+        }
+    }
+}
+
+/// Return the ids of the ports selected by a port argument
+/// A numeric argument selects the port with that id, any other argument selects
+/// all ports whose name contains it, ignoring case
+pub fn select_ports<T: MidiIO>(
+    midi_io: &T,
+    port: &str,
+    direction: &str,
+) -> Result<Vec<usize>, MidimonError> {
+    if let Ok(port_id) = port.parse::<usize>() {
+        return Ok(vec![port_id]);
+    }
+
+    let pattern = port.to_lowercase();
+    let mut port_ids = Vec::new();
+    let mut port_list = Vec::new();
+
+    for (i, io_port) in midi_io.ports().iter().enumerate() {
+        let port_name = midi_io.port_name(io_port)?;
+        if port_name.to_lowercase().contains(&pattern) {
+            port_ids.push(i);
+        }
+        port_list.push(format!("  ({}) {}", i, port_name));
+    }
+
+    if port_ids.is_empty() {
+        let mut message = format!("No {} port matching \"{}\".", direction, port);
+        if port_list.is_empty() {
+            message.push_str(&format!(" No {} ports available.", direction));
+        } else {
+            message.push_str(&format!(" Available {} ports:\n", direction));
+            message.push_str(&port_list.join("\n"));
+        }
+        return Err(MidimonError::PortNotFound(message));
+    }
+
+    Ok(port_ids)
+}
+
+/// Return port ids sorted and without duplicates
+/// A port matching several port arguments would be connected and reported multiple times otherwise.
+fn unique_port_ids(mut port_ids: Vec<usize>) -> Vec<usize> {
+    port_ids.sort_unstable();
+    port_ids.dedup();
+    port_ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter() {
+        let monitor = Monitor::new()
+            .ignore(Status::TimingClock)
+            .channel(0)
+            .channel(9);
+
+        let accepts = |data: &[u8]| monitor.filter.accepts(&MidiMessage::from_array(data));
+
+        assert!(accepts(&[0x90, 60, 100]));
+        assert!(accepts(&[0x89, 36, 0]));
+        assert!(!accepts(&[0x91, 60, 100]));
+        assert!(!accepts(&[0xF8]));
+        assert!(accepts(&[0xFA]));
+    }
+
+    #[test]
+    fn duplicate_ports() {
+        assert_eq!(unique_port_ids(vec![2, 0, 2, 1, 0]), [0, 1, 2]);
+        assert_eq!(unique_port_ids(Vec::new()), []);
+    }
+
+    #[test]
+    fn missing_callback() {
+        let result = Monitor::new().port("none").start();
+        assert!(matches!(result, Err(MidimonError::InvalidArgument(_))));
+    }
+}
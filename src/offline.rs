@@ -0,0 +1,71 @@
+//! Offline decoding of messages from a file or stdin
+
+use std::io::Read;
+use std::path::PathBuf;
+
+use midimon::display::{display_default, display_malformed, DisplayOptions, TimestampFormat};
+use midimon::error::MidimonError;
+use midimon::messages::MidiMessage;
+use midimon::parse::{parse_capture_line, RunningStatusParser};
+
+/// Decode and display all messages from a file or stdin if no file is given
+/// Text input is read line by line in min, min-hex or json format, any other input
+/// is treated as a binary byte stream. Messages using running status are completed.
+pub fn decode(file: Option<&PathBuf>, options: &DisplayOptions) -> Result<(), MidimonError> {
+    let mut input = Vec::new();
+
+    match file {
+        Some(path) => input = std::fs::read(path)?,
+        None => {
+            std::io::stdin().read_to_end(&mut input)?;
+        }
+    }
+
+    let chunks = match std::str::from_utf8(&input) {
+        Ok(text) if text.lines().any(|line| parse_capture_line(line).is_some()) => text
+            .lines()
+            .filter_map(parse_capture_line)
+            .map(|(timestamp, bytes)| (timestamp.unwrap_or(0), bytes))
+            .collect(),
+        _ => vec![(0, input)],
+    };
+
+    // Captured timestamps are not related to the time of day
+    let mut options = *options;
+    if let TimestampFormat::Wallclock = options.timestamp_format {
+        options.timestamp_format = TimestampFormat::Raw;
+    }
+
+    let mut parser = RunningStatusParser::new();
+    let mut previous_timestamp = None;
+    let mut last_timestamp = 0;
+
+    for (timestamp, bytes) in chunks {
+        let shown_timestamp = match options.timestamp_format {
+            TimestampFormat::Delta => {
+                timestamp.saturating_sub(previous_timestamp.replace(timestamp).unwrap_or(timestamp))
+            }
+            _ => timestamp,
+        };
+        for message in parser.parse(&bytes) {
+            display(shown_timestamp, &message, &options);
+        }
+        last_timestamp = shown_timestamp;
+    }
+
+    if let Some(message) = parser.flush() {
+        display(last_timestamp, &message, &options);
+    }
+
+    Ok(())
+}
+
+/// Display a decoded message in default format
+fn display(timestamp: u64, message: &MidiMessage, options: &DisplayOptions) {
+    let line = if message.is_valid() {
+        display_default(0, timestamp, &message.data, options)
+    } else {
+        display_malformed(0, timestamp, &message.data, options)
+    };
+    println!("{}", line);
+}
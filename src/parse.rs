@@ -0,0 +1,352 @@
+//! Parsing of captured messages
+
+use crate::messages::{MidiMessage, Status};
+
+/// Parse a list of bytes in min or min-hex format, e.g. `144, 60, 100` or `0x90, 0x3C, 0x64`
+pub fn parse_bytes(line: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+
+    for value in line.split(',') {
+        let value = value.trim();
+        let byte = match value
+            .strip_prefix("0x")
+            .or_else(|| value.strip_prefix("0X"))
+        {
+            Some(hex) => u8::from_str_radix(hex, 16).ok()?,
+            None => value.parse::<u8>().ok()?,
+        };
+        bytes.push(byte);
+    }
+
+    if bytes.is_empty() {
+        None
+    } else {
+        Some(bytes)
+    }
+}
+
+/// Parse a list of hex bytes separated by commas or whitespace, e.g. `90,3C,40` or `0x90 0x3c 0x40`
+pub fn parse_hex_bytes(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+
+    for value in text.split(|c: char| c == ',' || c.is_whitespace()) {
+        if value.is_empty() {
+            continue;
+        }
+        let hex = value
+            .strip_prefix("0x")
+            .or_else(|| value.strip_prefix("0X"))
+            .unwrap_or(value);
+        if hex.is_empty() || hex.len() > 2 {
+            return None;
+        }
+        bytes.push(u8::from_str_radix(hex, 16).ok()?);
+    }
+
+    if bytes.is_empty() {
+        None
+    } else {
+        Some(bytes)
+    }
+}
+
+/// Parse a message object in json format and return timestamp and bytes
+pub fn parse_json(line: &str) -> Option<(u64, Vec<u8>)> {
+    let line = line.trim();
+    if !line.starts_with('{') {
+        return None;
+    }
+
+    let timestamp = json_field(line, "timestamp")?;
+    let end = timestamp.find(|c: char| !c.is_ascii_digit())?;
+    let timestamp = timestamp[..end].parse::<u64>().ok()?;
+
+    let raw = json_field(line, "raw")?.strip_prefix('[')?;
+    let end = raw.find(']')?;
+    let bytes = parse_bytes(&raw[..end])?;
+
+    Some((timestamp, bytes))
+}
+
+/// Parse a line of a captured file in min, min-hex or json format
+/// The timestamp is only available for the json format. Comments starting with `#` after the
+/// bytes in min and min-hex format are ignored.
+pub fn parse_capture_line(line: &str) -> Option<(Option<u64>, Vec<u8>)> {
+    if let Some((timestamp, bytes)) = parse_json(line) {
+        return Some((Some(timestamp), bytes));
+    }
+
+    let bytes = line.split_once('#').map_or(line, |(bytes, _)| bytes);
+    parse_bytes(bytes).map(|bytes| (None, bytes))
+}
+
+/// Pattern of message bytes in hex with `??` as wildcard for any byte, e.g. `B0 07 ??`
+/// A message matches if it has the same length and all bytes except the wildcards are equal
+#[derive(Debug, Clone, PartialEq)]
+pub struct HexPattern {
+    bytes: Vec<Option<u8>>,
+}
+
+impl HexPattern {
+    /// Return if a message matches the pattern
+    pub fn matches(&self, message: &[u8]) -> bool {
+        message.len() == self.bytes.len()
+            && self
+                .bytes
+                .iter()
+                .zip(message)
+                .all(|(pattern, byte)| pattern.map_or(true, |pattern| pattern == *byte))
+    }
+}
+
+impl std::str::FromStr for HexPattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bytes = Vec::new();
+
+        for token in s.split_whitespace() {
+            let byte = match token {
+                "??" => None,
+                _ if token.len() == 2 => Some(
+                    u8::from_str_radix(token, 16)
+                        .map_err(|_| format!("invalid byte \"{}\", must be hex or ??", token))?,
+                ),
+                _ => return Err(format!("invalid byte \"{}\", must be hex or ??", token)),
+            };
+            bytes.push(byte);
+        }
+
+        if bytes.is_empty() {
+            return Err(String::from("pattern is empty"));
+        }
+
+        Ok(Self { bytes })
+    }
+}
+
+impl std::fmt::Display for HexPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bytes: Vec<String> = self
+            .bytes
+            .iter()
+            .map(|byte| byte.map_or(String::from("??"), |byte| format!("{:02X}", byte)))
+            .collect();
+        write!(f, "{}", bytes.join(" "))
+    }
+}
+
+/// Return the text following a field name in a json object
+fn json_field<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let key = format!("\"{}\":", name);
+    let start = line.find(&key)? + key.len();
+    Some(line[start..].trim_start())
+}
+
+/// Parser splitting a byte stream into messages
+/// Data bytes without a status byte are completed with the last channel status (running status)
+#[derive(Default)]
+pub struct RunningStatusParser {
+    running_status: Option<u8>,
+    buffer: Vec<u8>,
+}
+
+impl RunningStatusParser {
+    /// Create a new parser without running status
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse bytes and return all messages completed by them
+    /// Incomplete messages are kept until more bytes are parsed
+    pub fn parse(&mut self, bytes: &[u8]) -> Vec<MidiMessage> {
+        let mut messages = Vec::new();
+
+        for &byte in bytes {
+            self.push(byte, &mut messages);
+        }
+
+        messages
+    }
+
+    /// Return the incomplete message left over from previous bytes, if any
+    pub fn flush(&mut self) -> Option<MidiMessage> {
+        if self.buffer.is_empty() {
+            None
+        } else {
+            Some(MidiMessage::from_vec(std::mem::take(&mut self.buffer)))
+        }
+    }
+
+    /// Process a single byte
+    fn push(&mut self, byte: u8, messages: &mut Vec<MidiMessage>) {
+        let in_sysex = self.buffer.first() == Some(&(Status::SystemExclusive as u8));
+
+        if byte >= Status::TimingClock as u8 {
+            // Realtime messages may appear anywhere and don't affect running status
+            messages.push(MidiMessage::from_array(&[byte]));
+            return;
+        }
+
+        if byte == Status::EndOfExclusive as u8 {
+            if in_sysex {
+                self.buffer.push(byte);
+                messages.extend(self.flush());
+            } else {
+                messages.extend(self.flush());
+                messages.push(MidiMessage::from_array(&[byte]));
+            }
+            return;
+        }
+
+        if byte >= 0x80 {
+            // Any other status byte terminates an incomplete message
+            messages.extend(self.flush());
+            self.running_status = if byte < 0xF0 { Some(byte) } else { None };
+            self.buffer.push(byte);
+        } else if in_sysex || !self.buffer.is_empty() {
+            self.buffer.push(byte);
+        } else if let Some(status) = self.running_status {
+            self.buffer.push(status);
+            self.buffer.push(byte);
+        } else {
+            // Data byte without any status is discarded
+            return;
+        }
+
+        if self.buffer[0] == Status::SystemExclusive as u8 {
+            return;
+        }
+
+        let expected_len = MidiMessage::from_array(&self.buffer[..1])
+            .expected_len()
+            .unwrap_or(1);
+        if self.buffer.len() >= expected_len {
+            messages.extend(self.flush());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_all(bytes: &[u8]) -> Vec<Vec<u8>> {
+        RunningStatusParser::new()
+            .parse(bytes)
+            .into_iter()
+            .map(|message| message.data)
+            .collect()
+    }
+
+    #[test]
+    fn capture_line_comments() {
+        assert_eq!(
+            parse_capture_line("0xA0, 0x3C, 0x0A  # Poly Key Pressure"),
+            Some((None, vec![0xA0, 0x3C, 0x0A]))
+        );
+        assert_eq!(parse_capture_line("# Note On"), None);
+    }
+
+    #[test]
+    fn hex_bytes() {
+        assert_eq!(parse_hex_bytes("90,3C,40"), Some(vec![0x90, 0x3C, 0x40]));
+        assert_eq!(
+            parse_hex_bytes("0x90 0x3c, 0x40"),
+            Some(vec![0x90, 0x3C, 0x40])
+        );
+        assert_eq!(parse_hex_bytes("F8"), Some(vec![0xF8]));
+        assert_eq!(parse_hex_bytes(""), None);
+        assert_eq!(parse_hex_bytes("90,3C,400"), None);
+        assert_eq!(parse_hex_bytes("90,XY"), None);
+    }
+
+    #[test]
+    fn hex_patterns() {
+        let pattern: HexPattern = "B0 07 ??".parse().unwrap();
+        assert_eq!(pattern.to_string(), "B0 07 ??");
+        assert!(pattern.matches(&[0xB0, 0x07, 0x64]));
+        assert!(pattern.matches(&[0xB0, 0x07, 0x00]));
+        assert!(!pattern.matches(&[0xB1, 0x07, 0x64]));
+        assert!(!pattern.matches(&[0xB0, 0x07]));
+        assert!(!pattern.matches(&[0xB0, 0x07, 0x64, 0x00]));
+
+        let pattern: HexPattern = "?? 3c".parse().unwrap();
+        assert!(pattern.matches(&[0xC0, 0x3C]));
+
+        assert!("".parse::<HexPattern>().is_err());
+        assert!("B0 7".parse::<HexPattern>().is_err());
+        assert!("B0 GG".parse::<HexPattern>().is_err());
+        assert!("B0 ???".parse::<HexPattern>().is_err());
+    }
+
+    #[test]
+    fn complete_messages() {
+        assert_eq!(
+            parse_all(&[0x90, 60, 100, 0xC0, 5, 0xE0, 0, 64]),
+            vec![vec![0x90, 60, 100], vec![0xC0, 5], vec![0xE0, 0, 64]]
+        );
+    }
+
+    #[test]
+    fn running_status() {
+        assert_eq!(
+            parse_all(&[0x90, 60, 100, 62, 100, 60, 0]),
+            vec![vec![0x90, 60, 100], vec![0x90, 62, 100], vec![0x90, 60, 0]]
+        );
+    }
+
+    #[test]
+    fn running_status_two_byte_messages() {
+        assert_eq!(
+            parse_all(&[0xD0, 10, 20, 30]),
+            vec![vec![0xD0, 10], vec![0xD0, 20], vec![0xD0, 30]]
+        );
+    }
+
+    #[test]
+    fn realtime_keeps_running_status() {
+        assert_eq!(
+            parse_all(&[0xB0, 7, 100, 0xF8, 7, 0xFE, 90]),
+            vec![
+                vec![0xB0, 7, 100],
+                vec![0xF8],
+                vec![0xFE],
+                vec![0xB0, 7, 90]
+            ]
+        );
+    }
+
+    #[test]
+    fn realtime_within_message() {
+        assert_eq!(
+            parse_all(&[0x90, 60, 0xF8, 100]),
+            vec![vec![0xF8], vec![0x90, 60, 100]]
+        );
+    }
+
+    #[test]
+    fn system_common_cancels_running_status() {
+        assert_eq!(
+            parse_all(&[0x90, 60, 100, 0xF3, 1, 62, 100]),
+            vec![vec![0x90, 60, 100], vec![0xF3, 1]]
+        );
+    }
+
+    #[test]
+    fn sysex() {
+        assert_eq!(
+            parse_all(&[0xF0, 0x41, 0xF8, 0x10, 0xF7, 0x80, 60, 0]),
+            vec![vec![0xF8], vec![0xF0, 0x41, 0x10, 0xF7], vec![0x80, 60, 0]]
+        );
+    }
+
+    #[test]
+    fn incomplete_message() {
+        let mut parser = RunningStatusParser::new();
+        assert!(parser.parse(&[0x90, 60]).is_empty());
+        assert_eq!(parser.parse(&[100]).len(), 1);
+        assert!(parser.parse(&[0xB0, 7]).is_empty());
+        assert_eq!(parser.flush().unwrap().data, vec![0xB0, 7]);
+    }
+}
@@ -0,0 +1,82 @@
+//! Replay of captured messages to an output port
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use midimon::error::MidimonError;
+use midimon::monitor::select_ports;
+use midimon::parse::parse_capture_line;
+use midir::MidiOutput;
+
+use crate::output_client;
+
+/// Replay function arguments
+pub struct ReplayArgs {
+    pub file: PathBuf,
+    pub port: String,
+    pub speed: f64,
+    pub quiet: bool,
+    pub client_name: String,
+}
+
+/// Send all messages from a captured file to an output port
+/// Timing is reproduced from the timestamps if the file contains them
+pub fn replay(args: ReplayArgs) -> Result<(), MidimonError> {
+    if args.speed <= 0.0 {
+        return Err(MidimonError::InvalidArgument(String::from(
+            "Speed factor must be greater than 0.",
+        )));
+    }
+
+    let content = std::fs::read_to_string(&args.file)?;
+
+    let midi_out = MidiOutput::new(&output_client(&args.client_name))?;
+    let port_id = select_ports(&midi_out, &args.port, "output")?[0];
+    let out_port = midi_out
+        .ports()
+        .get(port_id)
+        .cloned()
+        .ok_or(MidimonError::PortNotFound(format!(
+            "No output port with id {}.",
+            port_id
+        )))?;
+    let port_name = midi_out.port_name(&out_port)?;
+    let mut connection = midi_out.connect(&out_port, "replay")?;
+
+    if !args.quiet {
+        println!("Sending to output port:");
+        println!("  ({}) {}", port_id, port_name);
+    }
+
+    let start = Instant::now();
+    let mut first_timestamp = None;
+    let mut count = 0;
+
+    for line in content.lines() {
+        let (timestamp, message) = match parse_capture_line(line) {
+            Some(capture) => capture,
+            None => continue,
+        };
+
+        if let Some(timestamp) = timestamp {
+            let first_timestamp = *first_timestamp.get_or_insert(timestamp);
+            let offset = timestamp.saturating_sub(first_timestamp) as f64 / args.speed;
+            let target = start + Duration::from_micros(offset as u64);
+            let now = Instant::now();
+            if target > now {
+                std::thread::sleep(target - now);
+            }
+        }
+
+        connection.send(&message)?;
+        count += 1;
+    }
+
+    connection.close();
+
+    if !args.quiet {
+        println!("{} messages sent.", count);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+//! Sending of single messages to an output port
+
+use std::time::Duration;
+
+use midimon::error::MidimonError;
+use midimon::messages::MidiMessage;
+use midimon::monitor::select_ports;
+use midir::MidiOutput;
+
+use crate::output_client;
+
+/// Send function arguments
+pub struct SendArgs {
+    pub port: String,
+    pub messages: Vec<MidiMessage>,
+    /// Time to wait between the messages
+    pub interval: Option<Duration>,
+    pub quiet: bool,
+    pub client_name: String,
+}
+
+/// Send the messages to an output port in the given order
+pub fn send(args: SendArgs) -> Result<(), MidimonError> {
+    if args.messages.is_empty() {
+        return Err(MidimonError::InvalidArgument(String::from(
+            "No messages to send. Use --note-on, --note-off, --cc, --program or --bytes.",
+        )));
+    }
+
+    let midi_out = MidiOutput::new(&output_client(&args.client_name))?;
+    let port_id = select_ports(&midi_out, &args.port, "output")?[0];
+    let out_port = midi_out
+        .ports()
+        .get(port_id)
+        .cloned()
+        .ok_or(MidimonError::PortNotFound(format!(
+            "No output port with id {}.",
+            port_id
+        )))?;
+    let port_name = midi_out.port_name(&out_port)?;
+    let mut connection = midi_out.connect(&out_port, "send")?;
+
+    if !args.quiet {
+        println!("Sending to output port:");
+        println!("  ({}) {}", port_id, port_name);
+    }
+
+    for (index, message) in args.messages.iter().enumerate() {
+        if let Some(interval) = args.interval.filter(|_| index > 0) {
+            std::thread::sleep(interval);
+        }
+        connection.send(&message.data)?;
+    }
+
+    connection.close();
+
+    if !args.quiet {
+        println!("{} messages sent.", args.messages.len());
+    }
+
+    Ok(())
+}
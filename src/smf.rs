@@ -0,0 +1,163 @@
+//! Recording of messages into Standard MIDI Files
+
+use std::io::Write;
+
+use crate::messages::{MidiMessage, Status};
+
+/// Ticks per quarter note
+pub const DIVISION: u16 = 480;
+
+/// Tempo in microseconds per quarter note, equivalent to 120 BPM
+pub const TEMPO: u32 = 500_000;
+
+/// Recorder collecting messages into a single track for a Type 0 Standard MIDI File
+#[derive(Default)]
+pub struct SmfRecorder {
+    track: Vec<u8>,
+    start_timestamp: Option<u64>,
+    last_tick: u64,
+}
+
+impl SmfRecorder {
+    /// Create a new recorder without any events
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a message with its timestamp in microseconds
+    /// System common and realtime messages are skipped because they can't be stored in a file.
+    /// Malformed messages are skipped too, they would break the alignment of all later events.
+    pub fn add(&mut self, timestamp: u64, message: &[u8]) {
+        let Some(&status) = message.first() else {
+            return;
+        };
+
+        if !MidiMessage::from_array(message).is_valid() {
+            return;
+        }
+
+        let event = if status == Status::SystemExclusive as u8 {
+            let mut event = vec![status];
+            write_vlq(&mut event, message.len() as u32 - 1);
+            event.extend_from_slice(&message[1..]);
+            event
+        } else if (0x80..0xF0).contains(&status) {
+            message.to_vec()
+        } else {
+            return;
+        };
+
+        let start = *self.start_timestamp.get_or_insert(timestamp);
+        let tick = timestamp.saturating_sub(start) * DIVISION as u64 / TEMPO as u64;
+        let delta = tick.saturating_sub(self.last_tick);
+        self.last_tick = self.last_tick.max(tick);
+
+        write_vlq(&mut self.track, delta.min(0x0FFF_FFFF) as u32);
+        self.track.extend_from_slice(&event);
+    }
+
+    /// Return the complete file contents
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut track = Vec::new();
+
+        // Tempo meta event
+        track.extend_from_slice(&[0x00, 0xFF, 0x51, 0x03]);
+        track.extend_from_slice(&TEMPO.to_be_bytes()[1..]);
+
+        track.extend_from_slice(&self.track);
+
+        // End of track meta event
+        track.extend_from_slice(&[0x00, 0xFF, 0x2F, 0x00]);
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"MThd");
+        bytes.extend_from_slice(&6u32.to_be_bytes());
+        bytes.extend_from_slice(&0u16.to_be_bytes());
+        bytes.extend_from_slice(&1u16.to_be_bytes());
+        bytes.extend_from_slice(&DIVISION.to_be_bytes());
+        bytes.extend_from_slice(b"MTrk");
+        bytes.extend_from_slice(&(track.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&track);
+
+        bytes
+    }
+
+    /// Write the complete file contents
+    pub fn write<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&self.to_bytes())
+    }
+}
+
+/// Append a value as variable-length quantity
+fn write_vlq(bytes: &mut Vec<u8>, value: u32) {
+    let mut groups = vec![(value & 0x7F) as u8];
+    let mut value = value >> 7;
+
+    while value > 0 {
+        groups.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+
+    bytes.extend(groups.iter().rev());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vlq(value: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_vlq(&mut bytes, value);
+        bytes
+    }
+
+    #[test]
+    fn variable_length_quantities() {
+        assert_eq!(vlq(0), vec![0x00]);
+        assert_eq!(vlq(0x7F), vec![0x7F]);
+        assert_eq!(vlq(0x80), vec![0x81, 0x00]);
+        assert_eq!(vlq(0x2000), vec![0xC0, 0x00]);
+        assert_eq!(vlq(0x0FFF_FFFF), vec![0xFF, 0xFF, 0xFF, 0x7F]);
+    }
+
+    #[test]
+    fn empty_file() {
+        let bytes = SmfRecorder::new().to_bytes();
+        assert_eq!(&bytes[..14], b"MThd\0\0\0\x06\0\0\0\x01\x01\xE0");
+        assert_eq!(&bytes[14..22], b"MTrk\0\0\0\x0B");
+        assert_eq!(
+            &bytes[22..],
+            &[0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20, 0x00, 0xFF, 0x2F, 0x00]
+        );
+    }
+
+    #[test]
+    fn events() {
+        let mut recorder = SmfRecorder::new();
+        recorder.add(1_000_000, &[0x90, 60, 100]);
+        recorder.add(1_000_000, &[0xF8]);
+        recorder.add(1_500_000, &[0x80, 60, 0]);
+        recorder.add(1_500_000, &[0xF0, 0x7E, 0x7F, 0xF7]);
+
+        assert_eq!(
+            recorder.track,
+            vec![
+                0x00, 0x90, 60, 100, // note on at start
+                0x83, 0x60, 0x80, 60, 0, // note off after 480 ticks
+                0x00, 0xF0, 0x03, 0x7E, 0x7F, 0xF7, // sysex
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_messages() {
+        let mut recorder = SmfRecorder::new();
+        recorder.add(0, &[0x90, 60]);
+        recorder.add(0, &[0xB0, 7, 0x90]);
+        recorder.add(0, &[0xF0, 0x7E, 0x7F]);
+        recorder.add(0, &[0x90, 60, 100]);
+
+        assert_eq!(recorder.track, vec![0x00, 0x90, 60, 100]);
+    }
+}
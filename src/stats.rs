@@ -0,0 +1,655 @@
+//! Message statistics for the session summary
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::decode::{CLOCKS_PER_QUARTER, CLOCK_TIMEOUT};
+use crate::display::{channel_number, DisplayOptions};
+use crate::messages::{MidiMessage, Status};
+
+/// Message counters collected during a session
+#[derive(Default)]
+pub struct Stats {
+    pub total: u64,
+    pub status_counts: BTreeMap<u8, u64>,
+    pub channel_counts: [u64; 16],
+    pub lowest_note: Option<u8>,
+    pub highest_note: Option<u8>,
+    /// Number of channel messages
+    pub channel_total: u64,
+    /// Number of system common and exclusive messages, including malformed ones
+    pub system_total: u64,
+    /// Number of system realtime messages
+    pub realtime_total: u64,
+    /// Count realtime messages only in their own category, not in the total and per type
+    pub omit_realtime: bool,
+    /// Channel (0-15) to count all channel messages on instead of their own
+    pub assume_channel: Option<u8>,
+}
+
+impl Stats {
+    /// Count a received message
+    pub fn add(&mut self, message: &[u8]) {
+        let msg = MidiMessage::from_array(message);
+
+        let realtime = is_realtime(message);
+        if msg.channel().is_some() {
+            self.channel_total += 1;
+        } else if realtime {
+            self.realtime_total += 1;
+        } else {
+            self.system_total += 1;
+        }
+
+        if realtime && self.omit_realtime {
+            return;
+        }
+
+        let status = match msg.status() {
+            Status::Error => message.first().copied().unwrap_or(0),
+            status => status as u8,
+        };
+
+        self.total += 1;
+        *self.status_counts.entry(status).or_insert(0) += 1;
+
+        if let Some(channel) = msg.channel() {
+            self.channel_counts[self.assume_channel.unwrap_or(channel) as usize] += 1;
+        }
+
+        if let (Status::NoteOn, Some(note), Some(velocity)) =
+            (msg.status(), msg.get_data(1), msg.get_data(2))
+        {
+            if velocity > 0 {
+                self.lowest_note = Some(self.lowest_note.map_or(note, |n| n.min(note)));
+                self.highest_note = Some(self.highest_note.map_or(note, |n| n.max(note)));
+            }
+        }
+    }
+
+    /// Return the session summary as text
+    /// The detailed summary adds the message rate, counts per channel and the note range
+    pub fn summary(&self, duration: Duration, detailed: bool, options: &DisplayOptions) -> String {
+        let mut lines = vec![
+            format!("Session duration: {:.3} s", duration.as_secs_f64()),
+            format!("Messages received: {}", self.total),
+        ];
+
+        if self.omit_realtime {
+            lines.push(format!(
+                "Realtime messages (not counted): {}",
+                self.realtime_total
+            ));
+        }
+
+        if detailed {
+            let seconds = duration.as_secs_f64();
+            let rate = if seconds > 0.0 {
+                self.total as f64 / seconds
+            } else {
+                0.0
+            };
+            lines.push(format!("Message rate: {:.1} messages/s", rate));
+            lines.push(String::new());
+            lines.push(String::from("Messages per category:"));
+            lines.push(format!("  Channel   {}", self.channel_total));
+            lines.push(format!("  System    {}", self.system_total));
+            lines.push(format!("  Realtime  {}", self.realtime_total));
+            lines.push(String::new());
+            lines.push(String::from("Messages per type:"));
+        }
+
+        // Errors are counted per first byte, which is shown to tell them apart
+        for (status, count) in &self.status_counts {
+            let name = match Status::try_from(*status) {
+                Ok(status) if status != Status::Error => status.to_string(),
+                _ => format!("{} ({:02X})", Status::Error, status),
+            };
+            lines.push(format!("  {:21}  {}", name, count));
+        }
+
+        if detailed {
+            lines.push(String::new());
+            lines.push(String::from("Messages per channel:"));
+            for (channel, count) in self.channel_counts.iter().enumerate() {
+                if *count > 0 {
+                    lines.push(format!(
+                        "  Ch:{:>2}  {}",
+                        channel_number(channel as u8, options),
+                        count
+                    ));
+                }
+            }
+
+            lines.push(String::new());
+            let note = |note: u8| {
+                let name = MidiMessage::from_array(&[Status::NoteOn as u8, note, 127])
+                    .note_name_with(&options.note_naming)
+                    .unwrap_or_default();
+                format!("{} ({})", name, note)
+            };
+            match (self.lowest_note, self.highest_note) {
+                (Some(lowest), Some(highest)) => {
+                    lines.push(format!("Lowest note:  {}", note(lowest)));
+                    lines.push(format!("Highest note: {}", note(highest)));
+                }
+                _ => lines.push(String::from("No notes received")),
+            }
+        }
+
+        lines.join("\n")
+    }
+
+    /// Return the channels that received any messages as text
+    pub fn channel_activity(&self, options: &DisplayOptions) -> String {
+        let channels: Vec<String> = self
+            .channel_counts
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(channel, count)| {
+                format!(
+                    "  Ch:{:>2}  {}",
+                    channel_number(channel as u8, options),
+                    count
+                )
+            })
+            .collect();
+
+        if channels.is_empty() {
+            return String::from("No channel messages received");
+        }
+
+        let mut lines = vec![format!("Active channels ({} of 16):", channels.len())];
+        lines.extend(channels);
+        lines.join("\n")
+    }
+}
+
+/// Return if a message is a system realtime message like timing clock or active sensing
+pub fn is_realtime(message: &[u8]) -> bool {
+    message
+        .first()
+        .is_some_and(|&status| status >= Status::TimingClock as u8)
+}
+
+/// Last value and value range of a controller
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CcStats {
+    pub last: u8,
+    pub min: u8,
+    pub max: u8,
+}
+
+/// Controller values per channel and controller number collected during a session
+#[derive(Default)]
+pub struct CcTracker {
+    pub controllers: BTreeMap<(u8, u8), CcStats>,
+    /// Channel (0-15) to track all controllers on instead of their own
+    pub assume_channel: Option<u8>,
+}
+
+impl CcTracker {
+    /// Register the value of a control change message, other messages are ignored
+    pub fn add(&mut self, message: &[u8]) {
+        let msg = MidiMessage::from_array(message);
+
+        if let (Status::ControlChange, Some(channel), Some(controller), Some(value)) = (
+            msg.status(),
+            msg.channel(),
+            msg.get_data(1),
+            msg.get_data(2),
+        ) {
+            self.controllers
+                .entry((self.assume_channel.unwrap_or(channel), controller))
+                .and_modify(|stats| {
+                    stats.last = value;
+                    stats.min = stats.min.min(value);
+                    stats.max = stats.max.max(value);
+                })
+                .or_insert(CcStats {
+                    last: value,
+                    min: value,
+                    max: value,
+                });
+        }
+    }
+
+    /// Return the controller values as table sorted by channel and controller number
+    pub fn table(&self, options: &DisplayOptions) -> String {
+        if self.controllers.is_empty() {
+            return String::from("No control changes received");
+        }
+
+        let mut lines = vec![String::from("Controller values:")];
+
+        for ((channel, controller), stats) in &self.controllers {
+            let name = MidiMessage::from_array(&[
+                Status::ControlChange as u8 | channel,
+                *controller,
+                stats.last,
+            ])
+            .cc_name()
+            .unwrap_or_default();
+            lines.push(format!(
+                "  Ch:{:>2}  No:{:>3}  Last:{:>3}  Min:{:>3}  Max:{:>3}  {}",
+                channel_number(*channel, options),
+                controller,
+                stats.last,
+                stats.min,
+                stats.max,
+                name
+            ));
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Number of velocities per bar of the velocity histogram
+const VELOCITY_BUCKET: usize = 8;
+
+/// Width of the longest bar of the velocity histogram in characters
+const HISTOGRAM_WIDTH: usize = 40;
+
+/// Note On velocity counts collected during a session
+pub struct VelocityHistogram {
+    pub counts: [u32; 128],
+}
+
+impl Default for VelocityHistogram {
+    fn default() -> Self {
+        Self { counts: [0; 128] }
+    }
+}
+
+impl VelocityHistogram {
+    /// Register the velocity of a note on message, other messages and velocity 0 are ignored
+    pub fn add(&mut self, message: &[u8]) {
+        let msg = MidiMessage::from_array(message);
+
+        if let (Status::NoteOn, Some(velocity)) = (msg.status(), msg.get_data(2)) {
+            if velocity > 0 {
+                self.counts[velocity as usize] += 1;
+            }
+        }
+    }
+
+    /// Return the velocities as histogram with a bar for each range of 8 velocities
+    pub fn histogram(&self) -> String {
+        let total: u32 = self.counts.iter().sum();
+        if total == 0 {
+            return String::from("No note on messages received");
+        }
+
+        let velocities = || (1..128).filter(|&velocity| self.counts[velocity] > 0);
+        let buckets: Vec<u32> = self
+            .counts
+            .chunks(VELOCITY_BUCKET)
+            .map(|bucket| bucket.iter().sum())
+            .collect();
+        let max_count = buckets.iter().copied().max().unwrap_or(1);
+
+        let mut lines = vec![format!(
+            "Note on velocities ({} notes, min {}, max {}):",
+            total,
+            velocities().min().unwrap_or(0),
+            velocities().max().unwrap_or(0)
+        )];
+
+        for (index, count) in buckets.iter().enumerate() {
+            let low = (index * VELOCITY_BUCKET).max(1);
+            let high = index * VELOCITY_BUCKET + VELOCITY_BUCKET - 1;
+            let width = (*count as usize * HISTOGRAM_WIDTH).div_ceil(max_count as usize);
+            lines.push(format!(
+                "  {:>3}-{:>3}  {:<width$}  {}",
+                low,
+                high,
+                "█".repeat(width),
+                count,
+                width = HISTOGRAM_WIDTH
+            ));
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Intervals between Timing Clock messages collected during a session
+#[derive(Default)]
+pub struct ClockJitter {
+    /// Timestamp of the last clock message per port in microseconds
+    last: BTreeMap<usize, u64>,
+    pub count: u64,
+    sum: f64,
+    sum_squares: f64,
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+}
+
+impl ClockJitter {
+    /// Register a clock message of a port with its timestamp in microseconds
+    /// Other messages are ignored. Intervals longer than the clock timeout are skipped because
+    /// the clock was stopped in between.
+    pub fn add(&mut self, port_id: usize, message: &[u8], timestamp: u64) {
+        if message.first() != Some(&(Status::TimingClock as u8)) {
+            return;
+        }
+
+        if let Some(last) = self.last.insert(port_id, timestamp) {
+            let interval = timestamp.saturating_sub(last);
+            if timestamp < last || interval > CLOCK_TIMEOUT {
+                return;
+            }
+
+            self.count += 1;
+            self.sum += interval as f64;
+            self.sum_squares += (interval as f64).powi(2);
+            self.min = Some(self.min.map_or(interval, |min| min.min(interval)));
+            self.max = Some(self.max.map_or(interval, |max| max.max(interval)));
+        }
+    }
+
+    /// Return the mean interval in microseconds
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+
+    /// Return the standard deviation of the intervals in microseconds
+    pub fn std_dev(&self) -> Option<f64> {
+        let mean = self.mean()?;
+        Some(
+            (self.sum_squares / self.count as f64 - mean.powi(2))
+                .max(0.0)
+                .sqrt(),
+        )
+    }
+
+    /// Return the interval statistics with the resulting tempo as text
+    pub fn summary(&self) -> String {
+        let (Some(min), Some(max), Some(mean), Some(std_dev)) =
+            (self.min, self.max, self.mean(), self.std_dev())
+        else {
+            return String::from("No timing clock intervals received");
+        };
+
+        let tempo = |interval: f64| 60e6 / (interval * CLOCKS_PER_QUARTER as f64);
+        let ms = |interval: f64| interval / 1000.0;
+
+        [
+            format!("Clock intervals ({} intervals):", self.count),
+            format!("  Min:     {:>8.3} ms", ms(min as f64)),
+            format!("  Max:     {:>8.3} ms", ms(max as f64)),
+            format!("  Mean:    {:>8.3} ms", ms(mean)),
+            format!(
+                "  Std dev: {:>8.3} ms ({:.2} %)",
+                ms(std_dev),
+                100.0 * std_dev / mean
+            ),
+            format!(
+                "  Tempo:   {:>8.1} BPM ({:.1}-{:.1})",
+                tempo(mean),
+                tempo(max as f64),
+                tempo(min as f64)
+            ),
+        ]
+        .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::{MiddleC, NoteNaming};
+
+    #[test]
+    fn message_categories() {
+        let messages = [
+            &[0x90, 60, 100][..],
+            &[0xF8],
+            &[0xF8],
+            &[0xFE],
+            &[0xF2, 0, 0],
+        ];
+
+        let mut stats = Stats::default();
+        for message in messages {
+            stats.add(message);
+        }
+        assert_eq!(stats.total, 5);
+        assert_eq!(
+            (
+                stats.channel_total,
+                stats.system_total,
+                stats.realtime_total
+            ),
+            (1, 1, 3)
+        );
+
+        let mut stats = Stats {
+            omit_realtime: true,
+            ..Default::default()
+        };
+        for message in messages {
+            stats.add(message);
+        }
+        assert_eq!(stats.total, 2);
+        assert_eq!(stats.realtime_total, 3);
+        assert!(!stats.status_counts.contains_key(&0xF8));
+
+        let summary = stats.summary(Duration::from_secs(1), true, &DisplayOptions::default());
+        assert!(
+            summary.contains("Realtime messages (not counted): 3"),
+            "{}",
+            summary
+        );
+        assert!(summary.contains("  Realtime  3"), "{}", summary);
+    }
+
+    #[test]
+    fn unknown_messages() {
+        let mut stats = Stats::default();
+        for message in [
+            &[0xF4][..],
+            &[0xF4],
+            &[0xFD],
+            &[0x05, 0x10],
+            &[0x90, 60, 100],
+        ] {
+            stats.add(message);
+        }
+
+        let summary = stats.summary(Duration::from_secs(1), false, &DisplayOptions::default());
+        let lines: Vec<&str> = summary.lines().skip(2).collect();
+        assert_eq!(
+            lines,
+            [
+                "  Error or unknown (05)  1",
+                "  Note On                1",
+                "  Error or unknown (F4)  2",
+                "  Error or unknown (FD)  1",
+            ]
+        );
+    }
+
+    #[test]
+    fn note_range() {
+        let mut stats = Stats::default();
+        for message in [&[0x90, 61, 100][..], &[0x90, 48, 90], &[0x90, 72, 0]] {
+            stats.add(message);
+        }
+
+        let summary = stats.summary(Duration::from_secs(1), true, &DisplayOptions::default());
+        assert!(
+            summary.ends_with("Lowest note:  C2 (48)\nHighest note: C#3 (61)"),
+            "{}",
+            summary
+        );
+
+        let options = DisplayOptions {
+            note_naming: NoteNaming {
+                middle_c: MiddleC::C4,
+                flats: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let summary = stats.summary(Duration::from_secs(1), true, &options);
+        assert!(
+            summary.ends_with("Lowest note:  C3 (48)\nHighest note: Db4 (61)"),
+            "{}",
+            summary
+        );
+    }
+
+    #[test]
+    fn channel_activity() {
+        let options = DisplayOptions::default();
+        let messages = [
+            &[0x90, 60, 100][..],
+            &[0x80, 60, 0],
+            &[0xB9, 7, 100],
+            &[0xF8],
+        ];
+
+        let mut stats = Stats::default();
+        assert_eq!(
+            stats.channel_activity(&options),
+            "No channel messages received"
+        );
+
+        for message in messages {
+            stats.add(message);
+        }
+        assert_eq!(
+            stats.channel_activity(&options),
+            "Active channels (2 of 16):\n  Ch: 1  2\n  Ch:10  1"
+        );
+
+        let zero_based = DisplayOptions {
+            zero_based_channels: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            stats.channel_activity(&zero_based),
+            "Active channels (2 of 16):\n  Ch: 0  2\n  Ch: 9  1"
+        );
+
+        let mut stats = Stats {
+            assume_channel: Some(4),
+            ..Default::default()
+        };
+        for message in messages {
+            stats.add(message);
+        }
+        assert_eq!(
+            stats.channel_activity(&options),
+            "Active channels (1 of 16):\n  Ch: 5  3"
+        );
+    }
+
+    #[test]
+    fn controller_values() {
+        let mut tracker = CcTracker::default();
+        assert_eq!(
+            tracker.table(&DisplayOptions::default()),
+            "No control changes received"
+        );
+
+        for message in [
+            &[0xB1, 7, 100][..],
+            &[0xB1, 7, 0],
+            &[0xB1, 7, 64],
+            &[0xB0, 74, 127],
+            &[0x91, 7, 127],
+            &[0xB0, 1],
+        ] {
+            tracker.add(message);
+        }
+
+        assert_eq!(tracker.controllers.len(), 2);
+        assert_eq!(
+            tracker.controllers[&(1, 7)],
+            CcStats {
+                last: 64,
+                min: 0,
+                max: 100
+            }
+        );
+
+        let table = tracker.table(&DisplayOptions::default());
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(
+            lines[1].starts_with("  Ch: 1  No: 74  Last:127"),
+            "{}",
+            table
+        );
+        assert!(
+            lines[2].starts_with("  Ch: 2  No:  7  Last: 64  Min:  0  Max:100  Volume"),
+            "{}",
+            table
+        );
+
+        let options = DisplayOptions {
+            zero_based_channels: true,
+            ..Default::default()
+        };
+        let table = tracker.table(&options);
+        assert!(table.contains("\n  Ch: 0  No: 74"), "{}", table);
+    }
+
+    #[test]
+    fn velocity_histogram() {
+        let mut histogram = VelocityHistogram::default();
+        assert_eq!(histogram.histogram(), "No note on messages received");
+
+        for message in [
+            &[0x90, 60, 100][..],
+            &[0x90, 62, 100],
+            &[0x91, 64, 127],
+            &[0x90, 60, 0],
+            &[0x80, 60, 64],
+            &[0x90, 60],
+        ] {
+            histogram.add(message);
+        }
+
+        assert_eq!(histogram.counts[100], 2);
+        assert_eq!(histogram.counts[127], 1);
+        assert_eq!(histogram.counts.iter().sum::<u32>(), 3);
+
+        let text = histogram.histogram();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 17);
+        assert_eq!(lines[0], "Note on velocities (3 notes, min 100, max 127):");
+        assert!(lines[1].starts_with("    1-  7      "), "{}", text);
+        assert!(lines[13].starts_with("   96-103  ████"), "{}", text);
+        assert!(lines[13].ends_with("  2"), "{}", text);
+        assert!(lines[16].ends_with("  1"), "{}", text);
+    }
+
+    #[test]
+    fn clock_jitter() {
+        let mut jitter = ClockJitter::default();
+        assert_eq!(jitter.summary(), "No timing clock intervals received");
+
+        for timestamp in [0, 20_000, 41_000, 61_000, 3_000_000, 3_020_000] {
+            jitter.add(0, &[0xF8], timestamp);
+        }
+        jitter.add(1, &[0xF8], 10_000);
+        jitter.add(0, &[0xFA], 3_030_000);
+
+        assert_eq!(jitter.count, 4);
+        assert_eq!(jitter.min, Some(20_000));
+        assert_eq!(jitter.max, Some(21_000));
+        assert_eq!(jitter.mean(), Some(20_250.0));
+        assert!((jitter.std_dev().unwrap() - 433.0).abs() < 0.1);
+
+        let summary = jitter.summary();
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(lines[0], "Clock intervals (4 intervals):");
+        assert_eq!(lines[3], "  Mean:      20.250 ms");
+        assert_eq!(lines[5], "  Tempo:      123.5 BPM (119.0-125.0)");
+    }
+}
@@ -0,0 +1,145 @@
+//! Decoding of manufacturer-specific System Exclusive messages
+
+pub mod roland;
+pub mod yamaha;
+
+/// Return the description of a decoded Roland or Yamaha parameter message
+pub fn decode(message: &[u8]) -> Option<String> {
+    if let Some(roland) = roland::RolandMessage::parse(message) {
+        return Some(roland.to_string());
+    }
+
+    yamaha::YamahaMessage::parse(message).map(|yamaha| yamaha.to_string())
+}
+
+/// Received and calculated checksum of a message
+#[derive(Debug, PartialEq)]
+pub struct ChecksumCheck {
+    pub received: u8,
+    pub expected: u8,
+}
+
+impl ChecksumCheck {
+    /// Return if the received checksum is the calculated one
+    pub fn valid(&self) -> bool {
+        self.received == self.expected
+    }
+}
+
+impl std::fmt::Display for ChecksumCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.valid() {
+            write!(f, "[checksum OK]")
+        } else {
+            write!(
+                f,
+                "[checksum BAD: got {:02X} expected {:02X}]",
+                self.received, self.expected
+            )
+        }
+    }
+}
+
+/// Length of a Sample Dump Standard data packet
+const SAMPLE_DUMP_PACKET_LEN: usize = 127;
+
+/// Return the checksums of a message in a recognized format that carries one
+/// Recognized are Roland DT1 and RQ1, Yamaha bulk dumps and Sample Dump Standard data packets
+pub fn verify_checksum(message: &[u8]) -> Option<ChecksumCheck> {
+    if let Some(roland) = roland::RolandMessage::parse(message) {
+        return Some(ChecksumCheck {
+            received: roland.checksum,
+            expected: roland.expected_checksum(),
+        });
+    }
+
+    if let Some((received, expected)) =
+        yamaha::YamahaMessage::parse(message).and_then(|yamaha| yamaha.checksums())
+    {
+        return Some(ChecksumCheck { received, expected });
+    }
+
+    // Sample dump data packet: F0 7E <device> 02 <packet> <120 bytes> <checksum> F7
+    if message.len() == SAMPLE_DUMP_PACKET_LEN
+        && message[..2] == [0xF0, 0x7E]
+        && message[3] == 0x02
+        && message[message.len() - 1] == 0xF7
+    {
+        return Some(ChecksumCheck {
+            received: message[message.len() - 2],
+            expected: xor_checksum(&message[1..message.len() - 2]),
+        });
+    }
+
+    None
+}
+
+/// Return bytes as space-separated hex values
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Return the checksum that makes the sum of all bytes including it a multiple of 128
+/// This is the scheme used by both Roland and Yamaha
+pub fn checksum(bytes: &[u8]) -> u8 {
+    let sum = bytes.iter().map(|&byte| byte as u32).sum::<u32>();
+    ((128 - sum % 128) % 128) as u8
+}
+
+/// Return the XOR of all bytes, limited to 7 bits
+/// This is the scheme used by Sample Dump Standard data packets
+pub fn xor_checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |checksum, &byte| checksum ^ byte) & 0x7F
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums() {
+        assert_eq!(checksum(&[]), 0);
+        assert_eq!(checksum(&[0x40, 0x00, 0x7F, 0x00]), 0x41);
+        assert_eq!(checksum(&[0x40, 0x01, 0x30, 0x00]), 0x0F);
+        assert_eq!(xor_checksum(&[]), 0);
+        assert_eq!(xor_checksum(&[0x7E, 0x00, 0x02, 0x01]), 0x7D);
+    }
+
+    #[test]
+    fn verify_checksums() {
+        let gs_reset = [
+            0xF0, 0x41, 0x10, 0x42, 0x12, 0x40, 0x00, 0x7F, 0x00, 0x41, 0xF7,
+        ];
+        let check = verify_checksum(&gs_reset).unwrap();
+        assert!(check.valid());
+        assert_eq!(check.to_string(), "[checksum OK]");
+
+        let mut bad = gs_reset;
+        bad[9] = 0x40;
+        assert_eq!(
+            verify_checksum(&bad).unwrap().to_string(),
+            "[checksum BAD: got 40 expected 41]"
+        );
+
+        let mut packet = vec![0xF0, 0x7E, 0x00, 0x02, 0x01];
+        packet.extend_from_slice(&[0x10; 120]);
+        packet.extend_from_slice(&[0x7D, 0xF7]);
+        assert!(verify_checksum(&packet).unwrap().valid());
+        packet[5] = 0x11;
+        assert!(!verify_checksum(&packet).unwrap().valid());
+
+        let xg_system_on = [0xF0, 0x43, 0x10, 0x4C, 0x00, 0x00, 0x7E, 0x00, 0xF7];
+        assert_eq!(verify_checksum(&xg_system_on), None);
+        assert_eq!(verify_checksum(&[0xF0, 0x42, 0x30, 0x00, 0xF7]), None);
+    }
+
+    #[test]
+    fn unknown_manufacturer() {
+        assert_eq!(decode(&[0xF0, 0x42, 0x30, 0x00, 0xF7]), None);
+        assert_eq!(decode(&[0xF0, 0x7E, 0x7F, 0x06, 0x01, 0xF7]), None);
+    }
+}
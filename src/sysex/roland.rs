@@ -0,0 +1,155 @@
+//! Roland data set (DT1) and data request (RQ1) messages
+
+use super::{checksum, hex_bytes};
+
+/// Manufacturer ID of Roland
+pub const MANUFACTURER_ID: u8 = 0x41;
+
+/// Command ID of data request messages
+pub const RQ1: u8 = 0x11;
+
+/// Command ID of data set messages
+pub const DT1: u8 = 0x12;
+
+/// Decoded Roland parameter message
+#[derive(Debug, PartialEq)]
+pub struct RolandMessage {
+    pub device: u8,
+    pub model: Vec<u8>,
+    pub command: u8,
+    pub address: Vec<u8>,
+    /// Data for DT1, requested size for RQ1
+    pub data: Vec<u8>,
+    pub checksum: u8,
+}
+
+impl RolandMessage {
+    /// Parse a complete SysEx message including F0 and F7
+    /// Model IDs consist of any number of leading zeros and one more byte.
+    /// Addresses are 3 bytes long for single-byte model IDs, 4 bytes otherwise.
+    pub fn parse(message: &[u8]) -> Option<Self> {
+        let (&last, message) = message.split_last()?;
+        if message.len() < 4 || message[0] != 0xF0 || message[1] != MANUFACTURER_ID || last != 0xF7
+        {
+            return None;
+        }
+
+        let device = message[2];
+        let model_len = message[3..].iter().take_while(|&&byte| byte == 0).count() + 1;
+        let command_index = 3 + model_len;
+        let command = *message.get(command_index)?;
+        if command != RQ1 && command != DT1 {
+            return None;
+        }
+
+        let (&checksum, body) = message[command_index + 1..].split_last()?;
+        let address_len = if model_len == 1 { 3 } else { 4 };
+        if body.len() < address_len {
+            return None;
+        }
+
+        Some(Self {
+            device,
+            model: message[3..command_index].to_vec(),
+            command,
+            address: body[..address_len].to_vec(),
+            data: body[address_len..].to_vec(),
+            checksum,
+        })
+    }
+
+    /// Return the checksum calculated from address and data
+    pub fn expected_checksum(&self) -> u8 {
+        let mut bytes = self.address.clone();
+        bytes.extend_from_slice(&self.data);
+        checksum(&bytes)
+    }
+
+    /// Return if the checksum matches address and data
+    pub fn checksum_valid(&self) -> bool {
+        self.expected_checksum() == self.checksum
+    }
+}
+
+impl std::fmt::Display for RolandMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (name, data_label) = if self.command == DT1 {
+            ("DT1", "Data")
+        } else {
+            ("RQ1", "Size")
+        };
+
+        write!(
+            f,
+            "Roland {}  Dev: {:02X}  Model: {}  Addr: {}  {}: {}  checksum: {}",
+            name,
+            self.device,
+            hex_bytes(&self.model),
+            hex_bytes(&self.address),
+            data_label,
+            hex_bytes(&self.data),
+            if self.checksum_valid() { "OK" } else { "BAD" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gs_reset() {
+        let message = [
+            0xF0, 0x41, 0x10, 0x42, 0x12, 0x40, 0x00, 0x7F, 0x00, 0x41, 0xF7,
+        ];
+        let roland = RolandMessage::parse(&message).unwrap();
+        assert_eq!(roland.address, vec![0x40, 0x00, 0x7F]);
+        assert_eq!(roland.data, vec![0x00]);
+        assert!(roland.checksum_valid());
+        assert_eq!(
+            roland.to_string(),
+            "Roland DT1  Dev: 10  Model: 42  Addr: 40 00 7F  Data: 00  checksum: OK"
+        );
+    }
+
+    #[test]
+    fn data_request_with_long_model_id() {
+        let message = [
+            0xF0, 0x41, 0x10, 0x00, 0x00, 0x00, 0x0E, 0x11, 0x19, 0x01, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x40, 0x26, 0xF7,
+        ];
+        let roland = RolandMessage::parse(&message).unwrap();
+        assert_eq!(roland.model, vec![0x00, 0x00, 0x00, 0x0E]);
+        assert_eq!(roland.address, vec![0x19, 0x01, 0x00, 0x00]);
+        assert_eq!(roland.data, vec![0x00, 0x00, 0x00, 0x40]);
+        assert!(roland.checksum_valid());
+        assert!(roland.to_string().starts_with("Roland RQ1"));
+    }
+
+    #[test]
+    fn bad_checksum() {
+        let message = [
+            0xF0, 0x41, 0x10, 0x42, 0x12, 0x40, 0x00, 0x7F, 0x00, 0x40, 0xF7,
+        ];
+        let roland = RolandMessage::parse(&message).unwrap();
+        assert!(!roland.checksum_valid());
+        assert!(roland.to_string().ends_with("checksum: BAD"));
+    }
+
+    #[test]
+    fn not_parameter_messages() {
+        assert_eq!(RolandMessage::parse(&[0xF0, 0x41, 0x10, 0x42, 0xF7]), None);
+        assert_eq!(
+            RolandMessage::parse(&[0xF0, 0x41, 0x10, 0x42, 0x13, 0x40, 0x00, 0x7F, 0x41, 0xF7]),
+            None
+        );
+        assert_eq!(
+            RolandMessage::parse(&[0xF0, 0x41, 0x10, 0x42, 0x12, 0x40, 0x41, 0xF7]),
+            None
+        );
+        assert_eq!(
+            RolandMessage::parse(&[0xF0, 0x41, 0x10, 0x42, 0x12, 0x40, 0x00, 0x7F, 0x00, 0x41]),
+            None
+        );
+    }
+}
@@ -0,0 +1,172 @@
+//! Yamaha parameter change and bulk dump messages
+
+use super::{checksum, hex_bytes};
+
+/// Manufacturer ID of Yamaha
+pub const MANUFACTURER_ID: u8 = 0x43;
+
+/// Kind of Yamaha message, given by the upper nibble of the byte after the manufacturer ID
+#[derive(Debug, PartialEq)]
+pub enum YamahaMessage {
+    /// Parameter change with 3-byte address and data
+    ParameterChange {
+        device: u8,
+        model: u8,
+        address: Vec<u8>,
+        data: Vec<u8>,
+    },
+    /// Bulk dump with byte count, 3-byte address, data and checksum
+    BulkDump {
+        device: u8,
+        model: u8,
+        byte_count: u16,
+        address: Vec<u8>,
+        data: Vec<u8>,
+        checksum: u8,
+    },
+}
+
+impl YamahaMessage {
+    /// Parse a complete SysEx message including F0 and F7
+    pub fn parse(message: &[u8]) -> Option<Self> {
+        let (&last, message) = message.split_last()?;
+        if message.len() < 4 || message[0] != 0xF0 || message[1] != MANUFACTURER_ID || last != 0xF7
+        {
+            return None;
+        }
+
+        let device = message[2] & 0x0F;
+        let model = message[3];
+
+        match message[2] & 0xF0 {
+            0x10 if message.len() > 7 => Some(YamahaMessage::ParameterChange {
+                device,
+                model,
+                address: message[4..7].to_vec(),
+                data: message[7..].to_vec(),
+            }),
+            0x00 if message.len() > 9 => {
+                let (&checksum, message) = message.split_last()?;
+                Some(YamahaMessage::BulkDump {
+                    device,
+                    model,
+                    byte_count: (message[4] as u16) << 7 | message[5] as u16,
+                    address: message[6..9].to_vec(),
+                    data: message[9..].to_vec(),
+                    checksum,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Return the received and the calculated checksum of a bulk dump
+    /// Parameter changes have no checksum
+    pub fn checksums(&self) -> Option<(u8, u8)> {
+        match self {
+            YamahaMessage::ParameterChange { .. } => None,
+            YamahaMessage::BulkDump {
+                byte_count,
+                address,
+                data,
+                checksum: received,
+                ..
+            } => {
+                let mut bytes = vec![(byte_count >> 7) as u8, (byte_count & 0x7F) as u8];
+                bytes.extend_from_slice(address);
+                bytes.extend_from_slice(data);
+                Some((*received, checksum(&bytes)))
+            }
+        }
+    }
+
+    /// Return if the checksum of a bulk dump matches byte count, address and data
+    /// Parameter changes have no checksum and are always valid
+    pub fn checksum_valid(&self) -> bool {
+        self.checksums()
+            .map_or(true, |(received, expected)| received == expected)
+    }
+}
+
+impl std::fmt::Display for YamahaMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            YamahaMessage::ParameterChange {
+                device,
+                model,
+                address,
+                data,
+            } => write!(
+                f,
+                "Yamaha Parameter Change  Dev: {:X}  Model: {:02X}  Addr: {}  Data: {}",
+                device,
+                model,
+                hex_bytes(address),
+                hex_bytes(data)
+            ),
+            YamahaMessage::BulkDump {
+                device,
+                model,
+                address,
+                data,
+                ..
+            } => write!(
+                f,
+                "Yamaha Bulk Dump  Dev: {:X}  Model: {:02X}  Addr: {}  ({} bytes)  checksum: {}",
+                device,
+                model,
+                hex_bytes(address),
+                data.len(),
+                if self.checksum_valid() { "OK" } else { "BAD" }
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xg_system_on() {
+        let message = [0xF0, 0x43, 0x10, 0x4C, 0x00, 0x00, 0x7E, 0x00, 0xF7];
+        let yamaha = YamahaMessage::parse(&message).unwrap();
+        assert!(yamaha.checksum_valid());
+        assert_eq!(
+            yamaha.to_string(),
+            "Yamaha Parameter Change  Dev: 0  Model: 4C  Addr: 00 00 7E  Data: 00"
+        );
+    }
+
+    #[test]
+    fn bulk_dump() {
+        let mut message = vec![0xF0, 0x43, 0x01, 0x4C, 0x00, 0x02, 0x08, 0x00, 0x00];
+        message.extend_from_slice(&[0x10, 0x20]);
+        message.push(checksum(&message[4..]));
+        message.push(0xF7);
+
+        let yamaha = YamahaMessage::parse(&message).unwrap();
+        assert!(yamaha.checksum_valid());
+        assert_eq!(
+            yamaha.to_string(),
+            "Yamaha Bulk Dump  Dev: 1  Model: 4C  Addr: 08 00 00  (2 bytes)  checksum: OK"
+        );
+
+        let len = message.len();
+        message[len - 2] ^= 0x01;
+        let yamaha = YamahaMessage::parse(&message).unwrap();
+        assert!(!yamaha.checksum_valid());
+    }
+
+    #[test]
+    fn other_messages() {
+        assert_eq!(
+            YamahaMessage::parse(&[0xF0, 0x43, 0x20, 0x4C, 0x00, 0x00, 0x7E, 0x00, 0xF7]),
+            None
+        );
+        assert_eq!(
+            YamahaMessage::parse(&[0xF0, 0x43, 0x10, 0x4C, 0x00, 0x00, 0xF7]),
+            None
+        );
+    }
+}